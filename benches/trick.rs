@@ -0,0 +1,59 @@
+//! トリックの勝敗判定の速さを比べます。`cargo bench`で実行します。
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand::seq::SliceRandom as _;
+use rand::SeedableRng as _;
+
+use napo::card::{Card, Suit};
+use napo::player::Players;
+use napo::rule::RuleSet;
+use napo::trick::{Play, Trick};
+use napo::trick_result::{judge, TrickCards, TrickResult};
+
+fn tricks() -> Vec<TrickCards> {
+    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(1);
+    let mut cards: Vec<Card> = Card::all().collect();
+    (0..1000)
+        .map(|_| {
+            cards.shuffle(&mut rng);
+            cards[..5].iter().copied().collect()
+        })
+        .collect()
+}
+
+fn bench_trick(c: &mut Criterion) {
+    let tricks = tricks();
+    let rules = RuleSet::default();
+    let players = Players::default();
+    let plays: Vec<Trick> = tricks
+        .iter()
+        .map(|cards| {
+            let mut trick = Trick::new(&rules);
+            for (p, c) in players.0.iter().zip(cards.iter()) {
+                trick.add(Play::new(p.clone(), *c)).unwrap();
+            }
+            trick
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("trick");
+    group.bench_function("TrickResult::new", |b| {
+        b.iter(|| {
+            for trick in plays.iter() {
+                black_box(
+                    TrickResult::new(black_box(trick), Some(Suit::Heart), 2, &rules).unwrap(),
+                );
+            }
+        })
+    });
+    group.bench_function("judge", |b| {
+        b.iter(|| {
+            for cards in tricks.iter() {
+                black_box(judge(black_box(cards), Some(Suit::Heart), 2, &rules));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_trick);
+criterion_main!(benches);
@@ -0,0 +1,158 @@
+//! 自動で席を埋めるプレイヤー。
+use crate::bidding::{Bid, Outcome};
+use crate::card::Card;
+use crate::declaration::Declaration;
+use crate::error::{Error, Result};
+use crate::player::Player;
+use crate::round::{Phase, Round};
+use crate::trick::Play;
+use crate::view::PlayerView;
+
+mod heuristic;
+mod ismcts;
+mod random;
+
+pub use heuristic::HeuristicAgent;
+pub use ismcts::{Budget, IsmctsAgent};
+pub use random::RandomAgent;
+
+/// 自分に見える情報だけから操作を選ぶ。選んだ操作はルールに従って検証される。
+pub trait Agent {
+    /// 競りで宣言する立ち。`None`はパス。
+    fn choose_bid(&mut self, view: &PlayerView) -> Option<Bid>;
+
+    /// 競りで決まったナポレオンとして宣言する。
+    fn choose_declaration(&mut self, view: &PlayerView) -> Declaration;
+
+    /// オープンと交換して捨てるカード。`view.opens`にオープンが入っていて、同じ枚数を捨てる。
+    fn choose_discards(&mut self, view: &PlayerView) -> Vec<Card>;
+
+    /// 出すカード。`view.legal_plays`から選ぶ。
+    fn choose_play(&mut self, view: &PlayerView) -> Card;
+}
+
+/// `player`の手番なら`agent`に選ばせて`round`を進める。手番でなければ何もせず`false`を返す。
+pub fn act(round: &mut Round, player: &Player, agent: &mut dyn Agent) -> Result<bool> {
+    if round.next_actor().as_ref() != Some(player) {
+        return Ok(false);
+    }
+    let view = PlayerView::new(round, player)?;
+    match round.phase() {
+        Phase::Bidding => match round.bidding().outcome() {
+            Some(Outcome::Napoleon { .. }) => {
+                round.set_declaration(agent.choose_declaration(&view))?
+            }
+            _ => match agent.choose_bid(&view) {
+                Some(bid) => round.bid(player, bid)?,
+                None => round.pass(player)?,
+            },
+        },
+        Phase::Exchange => round.exchange(player, agent.choose_discards(&view))?,
+        Phase::Playing { .. } => round.play(Play::new(player.clone(), agent.choose_play(&view)))?,
+        Phase::Dealing | Phase::Finished => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// 全員を`agents`に任せてラウンドを最後まで進める。`agents`は席順に全員分。
+pub fn play_round(round: &mut Round, agents: &mut [Box<dyn Agent>]) -> Result<()> {
+    while let Some(player) = round.next_actor() {
+        let seat = round
+            .field_players
+            .0
+            .iter()
+            .position(|p| p.player == player)
+            .ok_or(Error::UnknownPlayer(player.clone()))?;
+        act(round, &player, agents[seat].as_mut())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::Players;
+    use crate::settlement::Settlement;
+
+    fn random_agents(seed: u64) -> Vec<Box<dyn Agent>> {
+        (0..5)
+            .map(|i| Box::new(RandomAgent::new(seed * 5 + i)) as Box<dyn Agent>)
+            .collect()
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(1)]
+    #[case(2)]
+    #[case(3)]
+    fn test_play_round(#[case] seed: u64) -> anyhow::Result<()> {
+        let mut round = Round::new_with_seed(Players::default(), seed)?;
+        play_round(&mut round, &mut random_agents(seed))?;
+        assert_eq!(round.phase(), Phase::Finished);
+        let settlement = Settlement::new(&round)?;
+        assert_eq!(settlement.deltas.iter().map(|(_, d)| d).sum::<i32>(), 0);
+
+        // 同じシードなら同じ進み方になる
+        let mut again = Round::new_with_seed(Players::default(), seed)?;
+        play_round(&mut again, &mut random_agents(seed))?;
+        assert_eq!(again.events(), round.events());
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(1)]
+    #[case(2)]
+    fn test_play_round_with_joker(#[case] seed: u64) -> anyhow::Result<()> {
+        let rules = crate::rule::RuleSet {
+            joker: true,
+            ..Default::default()
+        };
+        let mut round = Round::new_with_rules(Players::default(), seed, rules)?;
+        let mut agents: Vec<Box<dyn Agent>> = vec![
+            Box::new(IsmctsAgent::new(seed, Budget::Iterations(50))),
+            Box::new(HeuristicAgent::new()),
+            Box::new(RandomAgent::new(seed)),
+            Box::new(HeuristicAgent::new()),
+            Box::new(RandomAgent::new(seed + 1)),
+        ];
+        play_round(&mut round, &mut agents)?;
+        assert_eq!(round.phase(), Phase::Finished);
+        assert_eq!(round.discards().map(<[Card]>::len), Some(3));
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(4, 1)]
+    #[case(6, 2)]
+    fn test_play_round_with_players(#[case] n: usize, #[case] seed: u64) -> anyhow::Result<()> {
+        let rules = crate::rule::RuleSet::for_players(n);
+        let mut round = Round::new_with_rules(Players::with_len(n), seed, rules)?;
+        let mut agents: Vec<Box<dyn Agent>> = (0..n as u64)
+            .map(|i| match i % 3 {
+                0 => Box::new(IsmctsAgent::new(seed + i, Budget::Iterations(50))) as Box<dyn Agent>,
+                1 => Box::new(HeuristicAgent::new()),
+                _ => Box::new(RandomAgent::new(seed + i)),
+            })
+            .collect();
+        play_round(&mut round, &mut agents)?;
+        assert_eq!(round.phase(), Phase::Finished);
+        let settlement = Settlement::new(&round)?;
+        assert_eq!(settlement.deltas.len(), n);
+        assert_eq!(settlement.deltas.iter().map(|(_, d)| d).sum::<i32>(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_act_out_of_turn() -> anyhow::Result<()> {
+        let players = Players::default();
+        let mut round = Round::new_with_seed(players.clone(), 1)?;
+        let mut agent = RandomAgent::new(1);
+        assert!(!act(&mut round, &players.0[1], &mut agent)?);
+        assert_eq!(round.events().len(), 1);
+        assert!(act(&mut round, &players.0[0], &mut agent)?);
+        assert_eq!(round.events().len(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,453 @@
+use super::Agent;
+use crate::bidding::Bid;
+use crate::card::{Card, Suit};
+use crate::card_set::CardSet;
+use crate::declaration::Declaration;
+use crate::player::{Player, Role};
+use crate::rule::RuleSet;
+use crate::trick_result::{trick_winner, TrickCards};
+use crate::view::PlayerView;
+
+/// 人数で決まる立ちの下限(5人なら13枚)で立つのに必要な手札の強さ。
+const BID_THRESHOLD: f64 = 3.5;
+/// 立ちを1枚増やすのに必要な手札の強さ。
+const BID_STEP: f64 = 0.8;
+const MAX_BID: usize = 16;
+
+/// 定石に従って選ぶ。
+///
+/// - 切り札と役札(マイティ、ジャック、よろめき)の強さを数えて立つ
+/// - 副官には自分が持っていない一番強いカードを指名する
+/// - 切り札以外の短いスートの弱いカードを捨てる
+/// - ナポレオンは切り札から出し、味方が勝っていれば追い越さずに絵札を渡し、敵が勝っていれば一番安く勝つ
+#[derive(Debug, Default, Clone)]
+pub struct HeuristicAgent;
+
+impl HeuristicAgent {
+    pub fn new() -> Self {
+        HeuristicAgent
+    }
+}
+
+/// `trump`を切り札にしたときの手札の強さ。おおよそ取れるトリックの数。
+fn strength(hands: &CardSet, trump: Suit) -> f64 {
+    hands
+        .iter()
+        .map(|c| {
+            if c.is_almighty() || c.is_joker() || (c.number == 11 && c.is_suit(trump)) {
+                1.0
+            } else if c.number == 11 && c.is_suit(trump.reverse()) {
+                0.8
+            } else if c.is_yoromeki() {
+                0.5
+            } else if c.is_suit(trump) {
+                match c.number {
+                    1 => 0.8,
+                    13 => 0.6,
+                    12 => 0.5,
+                    _ => 0.3,
+                }
+            } else if c.number == 1 {
+                0.5
+            } else {
+                0.0
+            }
+        })
+        .sum()
+}
+
+/// 勝つために使うときの惜しさ。役札(ジョーカーを含む)、切り札、数字の順に大きい。
+fn cost(card: &Card, trump: Option<Suit>) -> u8 {
+    let jack = |s: Suit| card.number == 11 && card.is_suit(s);
+    if card.is_almighty() {
+        100
+    } else if card.is_joker() {
+        95
+    } else if trump.is_some_and(jack) {
+        90
+    } else if trump.is_some_and(|s| jack(s.reverse())) {
+        80
+    } else if card.is_yoromeki() {
+        70
+    } else if trump.is_some_and(|s| card.is_suit(s)) {
+        50 + card.rank()
+    } else {
+        card.rank()
+    }
+}
+
+/// 副官に指名したいカードを強い順に。
+fn aide_candidates(trump: Option<Suit>) -> Vec<Card> {
+    let card = |number, suit| Card { number, suit };
+    let mut cards = vec![card(1, Suit::Spade)];
+    if let Some(s) = trump {
+        cards.extend([card(11, s), card(11, s.reverse()), card(1, s), card(13, s)]);
+    }
+    cards.push(card(12, Suit::Heart));
+    cards.extend(Suit::ALL.map(|s| card(1, s)));
+    cards.extend(Suit::ALL.map(|s| card(13, s)));
+    cards
+}
+
+struct Table<'a> {
+    view: &'a PlayerView,
+    trump: Option<Suit>,
+    rules: &'a RuleSet,
+    napoleon: Option<&'a Player>,
+}
+
+impl<'a> Table<'a> {
+    fn new(view: &'a PlayerView) -> Self {
+        let declaration = view.public.declaration.as_ref();
+        Table {
+            view,
+            trump: declaration.and_then(|d| d.suit),
+            rules: &view.public.rules,
+            napoleon: declaration.map(|d| &d.napoleon),
+        }
+    }
+
+    fn trick_no(&self) -> u8 {
+        self.view.public.trick_results.len() as u8 + 1
+    }
+
+    /// `player`が味方かどうか。副官が分からないうちは、連合軍はナポレオン以外を味方とみなす。
+    fn is_partner(&self, player: &Player) -> bool {
+        let me = &self.view.player;
+        let aide = self.view.public.aide.as_ref();
+        match self.view.role {
+            Role::Napoleon => aide == Some(player),
+            Role::Aide => self.napoleon == Some(player),
+            Role::Union => player != me && self.napoleon != Some(player) && aide != Some(player),
+        }
+    }
+
+    fn trick_cards(&self) -> TrickCards {
+        self.view
+            .public
+            .trick
+            .plays
+            .iter()
+            .map(|p| p.card)
+            .collect()
+    }
+
+    fn wins(&self, card: Card) -> bool {
+        let mut cards = self.trick_cards();
+        cards.push(card);
+        trick_winner(&cards, self.trump, self.trick_no(), self.rules) == cards.len() - 1
+    }
+
+    fn cheapest<I: Iterator<Item = Card>>(&self, cards: I) -> Option<Card> {
+        cards.min_by_key(|c| (c.is_face(), cost(c, self.trump)))
+    }
+
+    fn lead(&self) -> Card {
+        let legal = &self.view.legal_plays;
+        // ジョーカーは最初に出すと負けるので数えない
+        let trumps: Vec<Card> = legal
+            .iter()
+            .filter(|c| cost(c, self.trump) >= 50 && !c.is_joker())
+            .cloned()
+            .collect();
+        if self.view.role == Role::Napoleon && trumps.len() >= 2 {
+            return *trumps.iter().max_by_key(|c| cost(c, self.trump)).unwrap();
+        }
+        // 絵札を渡さないように、弱いカードから出す
+        self.cheapest(legal.iter().cloned()).unwrap()
+    }
+
+    fn follow(&self) -> Card {
+        let legal = &self.view.legal_plays;
+        let plays = &self.view.public.trick.plays;
+        let winner =
+            &plays[trick_winner(&self.trick_cards(), self.trump, self.trick_no(), self.rules)];
+        let is_last = plays.len() == self.rules.players - 1;
+        let safe = winner.card.is_almighty() || cost(&winner.card, self.trump) >= 80;
+
+        if self.is_partner(&winner.player) {
+            let ducks = legal.iter().filter(|c| !self.wins(**c));
+            if is_last || safe {
+                // 味方に絵札を渡す
+                if let Some(c) = ducks
+                    .clone()
+                    .filter(|c| c.is_face())
+                    .min_by_key(|c| cost(c, self.trump))
+                {
+                    return *c;
+                }
+            }
+            if let Some(c) = self.cheapest(ducks.cloned()) {
+                return c;
+            }
+            return self.cheapest(legal.iter().cloned()).unwrap();
+        }
+
+        let has_points = plays.iter().any(|p| p.card.is_face());
+        let winners = legal.iter().filter(|c| self.wins(**c)).cloned();
+        if let Some(c) = winners.min_by_key(|c| cost(c, self.trump)) {
+            if has_points || is_last || cost(&c, self.trump) < 50 {
+                return c;
+            }
+        }
+        self.cheapest(legal.iter().cloned()).unwrap()
+    }
+}
+
+impl Agent for HeuristicAgent {
+    fn choose_bid(&mut self, view: &PlayerView) -> Option<Bid> {
+        let (trump, s) = Suit::ALL
+            .iter()
+            .map(|t| (*t, strength(&view.hands, *t)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+        if s < BID_THRESHOLD {
+            return None;
+        }
+        let rules = view.public.rules;
+        let max = (rules.lowest_bid() + ((s - BID_THRESHOLD) / BID_STEP) as usize).min(MAX_BID);
+        let highest = view.public.highest_bid().map(|(_, b)| b);
+        let min = rules.min_bid;
+        Bid::all().find(|b| {
+            b.suit == Some(trump)
+                && (min..=max).contains(&b.number)
+                && highest.is_none_or(|h| *b > h)
+        })
+    }
+
+    fn choose_declaration(&mut self, view: &PlayerView) -> Declaration {
+        let (_, bid) = view.public.highest_bid().expect("bidding is decided");
+        // 候補を全て持っていれば、持っていないカードのどれかを指名する
+        let aide = aide_candidates(bid.suit)
+            .into_iter()
+            .chain(view.public.rules.deck())
+            .find(|c| !view.hands.contains(c))
+            .expect("hands never hold the whole deck");
+        Declaration::with_rules(
+            view.player.clone(),
+            bid.suit,
+            bid.number,
+            aide,
+            &view.public.rules,
+        )
+        .unwrap()
+    }
+
+    fn choose_discards(&mut self, view: &PlayerView) -> Vec<Card> {
+        let trump = view.public.declaration.as_ref().and_then(|d| d.suit);
+        let mut cards: Vec<Card> = view
+            .hands
+            .iter()
+            .chain(view.opens.iter().flatten().copied())
+            .collect();
+        // ジョーカーはどのスートにも数えない
+        let count = |card: Card| {
+            if card.is_joker() {
+                0
+            } else {
+                cards.iter().filter(|c| c.is_suit(card.suit)).count()
+            }
+        };
+        // 残す価値の低い順。絵札と切り札は残し、短いスートの弱いカードを捨ててスートを切らす
+        let keep: Vec<(Card, (bool, u8, usize, u8))> = cards
+            .iter()
+            .map(|c| {
+                let c = *c;
+                (c, (c.is_face(), cost(&c, trump) / 50, count(c), c.rank()))
+            })
+            .collect();
+        cards.sort_by_key(|c| keep.iter().find(|(k, _)| k == c).unwrap().1);
+        cards.truncate(view.public.rules.opens());
+        cards
+    }
+
+    fn choose_play(&mut self, view: &PlayerView) -> Card {
+        let table = Table::new(view);
+        if view.legal_plays.len() == 1 {
+            return view.legal_plays[0];
+        }
+        if view.public.trick.plays.is_empty() {
+            table.lead()
+        } else {
+            table.follow()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{play_round, RandomAgent};
+    use crate::player::Players;
+    use crate::round::Round;
+    use crate::settlement::Settlement;
+
+    #[rstest::rstest]
+    #[test]
+    #[case("SA SJ CJ SK SQ S10 S9 HA DA C5", None, Some("13S"))]
+    #[case("SA SJ CJ SK SQ S10 S9 HA DA C5", Some("14H"), Some("14S"))]
+    #[case("SA SJ CJ SK SQ S10 S9 HA DA C5", Some("15S"), None)]
+    #[case("HJ DJ HA HK H5 H7 S2 S3 C4 D5", None, Some("13H"))]
+    #[case("S2 S3 S4 H5 H6 D7 D8 C9 C10 CK", None, None)]
+    fn test_choose_bid(
+        #[case] hands: &str,
+        #[case] highest: Option<&str>,
+        #[case] bid: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let players = Players::default();
+        let mut round = Round::new_with_seed(players.clone(), 1)?;
+        round.field_players.0[1].hands = hands.parse()?;
+        match highest {
+            Some(b) => round.bid(&players.0[0], b.parse()?)?,
+            None => round.pass(&players.0[0])?,
+        }
+        let view = PlayerView::new(&round, &players.0[1])?;
+        let expected = bid.map(|b| b.parse()).transpose()?;
+        assert_eq!(HeuristicAgent::new().choose_bid(&view), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_choose_declaration() -> anyhow::Result<()> {
+        let players = Players::default();
+        let mut round = Round::new_with_seed(players.clone(), 1)?;
+        round.field_players.0[0].hands = "SA SJ S10 S9 S4 HA D3 D4 C5 C6".parse()?;
+        round.bid(&players.0[0], "13S".parse()?)?;
+        for p in players.0[1..].iter() {
+            round.pass(p)?;
+        }
+        let view = PlayerView::new(&round, &players.0[0])?;
+        let declaration = HeuristicAgent::new().choose_declaration(&view);
+        assert_eq!(declaration.aide, "CJ".parse()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_choose_declaration_all_candidates() -> anyhow::Result<()> {
+        let players = Players::default();
+        let mut round = Round::new_with_seed(players.clone(), 1)?;
+        let hands: crate::card_set::CardSet = "SA HA DA CA SK HK DK CK HQ D2".parse()?;
+        round.field_players.0[0].hands = hands;
+        round.bid(&players.0[0], "13NT".parse()?)?;
+        for p in players.0[1..].iter() {
+            round.pass(p)?;
+        }
+        let view = PlayerView::new(&round, &players.0[0])?;
+        let declaration = HeuristicAgent::new().choose_declaration(&view);
+        assert!(!hands.contains(&declaration.aide));
+        Ok(())
+    }
+
+    #[test]
+    fn test_choose_discards() -> anyhow::Result<()> {
+        let players = Players::default();
+        let mut round = Round::new_with_seed(players.clone(), 1)?;
+        let hands = "SA SJ S10 S9 S4 HA D3 D4 H5 C6".parse()?;
+        round.field_players.0[0].hands = hands;
+        round.opens = vec![Card::try_from(2)?, Card::try_from(49)?];
+        let aide = Card::try_from(50)?;
+        round.set_declaration(Declaration::new(
+            players.0[0].clone(),
+            Some(Suit::Spade),
+            13,
+            aide,
+        )?)?;
+        let view = PlayerView::new(&round, &players.0[0])?;
+        let discards = HeuristicAgent::new().choose_discards(&view);
+        assert_eq!(discards, vec![Card::try_from(29)?, Card::try_from(30)?]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_choose_discards_with_joker() -> anyhow::Result<()> {
+        let players = Players::default();
+        let rules = RuleSet {
+            joker: true,
+            ..Default::default()
+        };
+        let mut round = Round::new_with_rules(players.clone(), 1, rules)?;
+        round.field_players.0[0].hands = "JK S9 D2 D3 C2 C3 HA HK HJ H10".parse()?;
+        round.opens = crate::card::parse_hands("H2 H3 H4")?;
+        round.set_declaration(Declaration::new(
+            players.0[0].clone(),
+            Some(Suit::Heart),
+            13,
+            "DA".parse()?,
+        )?)?;
+        let view = PlayerView::new(&round, &players.0[0])?;
+        let mut discards = HeuristicAgent::new().choose_discards(&view);
+        discards.sort_by_key(|c| u8::from(*c));
+        // ジョーカーをスペードに数えないので、スペードを切らしに行く
+        let mut expected = crate::card::parse_hands("S9 D2 C2")?;
+        expected.sort_by_key(|c| u8::from(*c));
+        assert_eq!(discards, expected);
+        Ok(())
+    }
+
+    /// `n`人のラウンドで、ナポレオンが一番大きいダイヤを出したトリックに`seat`の席が切り札か捨て札で応える。
+    fn follow_trick(n: usize, seat: usize) -> anyhow::Result<Card> {
+        let players = Players::with_len(n);
+        let rules = RuleSet::for_players(n);
+        let mut round = Round::new_with_rules(players.clone(), 1, rules)?;
+        for (i, p) in round.field_players.0.iter_mut().enumerate() {
+            p.hands = match i {
+                0 => "D9".parse()?,
+                i if i == seat => "H2 C3".parse()?,
+                i => format!("D{}", i + 1).parse()?,
+            };
+        }
+        round.set_declaration(Declaration::with_rules(
+            players.0[0].clone(),
+            Some(Suit::Heart),
+            rules.min_bid,
+            "HA".parse()?,
+            &rules,
+        )?)?;
+        round.exchange(&players.0[0], round.opens.clone())?;
+        for p in players.0[..seat].iter() {
+            let card = round.legal_plays()[0];
+            round.play(crate::trick::Play::new(p.clone(), card))?;
+        }
+        let view = PlayerView::new(&round, &players.0[seat])?;
+        Ok(HeuristicAgent::new().choose_play(&view))
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(4)]
+    #[case(5)]
+    #[case(6)]
+    fn test_follow_last(#[case] n: usize) -> anyhow::Result<()> {
+        // 最後に出すなら絵札がなくても切り札で取る
+        assert_eq!(follow_trick(n, n - 1)?, "H2".parse()?);
+        // 後に出す人がいれば切り札を惜しむ
+        assert_eq!(follow_trick(n, n - 2)?, "C3".parse()?);
+        Ok(())
+    }
+
+    /// 1席だけ`agent`にして、残りはランダムに選んだときの、その席の得点の合計。
+    fn seat_score(n: u64, agent: impl Fn(u64) -> Box<dyn Agent>) -> anyhow::Result<i32> {
+        let mut total = 0;
+        for seed in 0..n {
+            let seat = (seed % 5) as usize;
+            let mut agents: Vec<Box<dyn Agent>> = (0..5)
+                .map(|i| Box::new(RandomAgent::new(seed * 5 + i)) as Box<dyn Agent>)
+                .collect();
+            agents[seat] = agent(seed);
+            let mut round = Round::new_with_seed(Players::default(), seed)?;
+            play_round(&mut round, &mut agents)?;
+            let player = &round.field_players.0[seat].player;
+            total += Settlement::new(&round)?.delta(player).unwrap();
+        }
+        Ok(total)
+    }
+
+    #[test]
+    fn test_beats_random() -> anyhow::Result<()> {
+        let heuristic = seat_score(200, |_| Box::new(HeuristicAgent::new()))?;
+        let random = seat_score(200, |seed| Box::new(RandomAgent::new(seed + 1000)))?;
+        assert!(heuristic > 0);
+        assert!(heuristic > random);
+        Ok(())
+    }
+}
@@ -0,0 +1,449 @@
+use std::time::{Duration, Instant};
+
+use rand::seq::{IteratorRandom as _, SliceRandom as _};
+use rand::SeedableRng as _;
+
+use super::{Agent, HeuristicAgent};
+use crate::bidding::Bid;
+use crate::card::{lead_suit, Card, Suit};
+use crate::card_set::CardSet;
+use crate::declaration::Declaration;
+use crate::playout::PlayState;
+use crate::round::Team;
+use crate::view::PlayerView;
+
+/// 1手を選ぶのに使う探索の量。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Budget {
+    /// 決まった回数だけ探索する。シードが同じなら同じ手を選ぶ。
+    Iterations(usize),
+    /// 決まった時間だけ探索する。計算機の速さで選ぶ手が変わる。
+    Time(Duration),
+}
+
+/// 見えていない手札を観測と矛盾しないように配り直し、配り直した手札の上で木を探索する(SO-ISMCTS)。
+///
+/// プレイ以外の競り、宣言、交換は`HeuristicAgent`に任せる。
+pub struct IsmctsAgent {
+    rng: rand_chacha::ChaCha8Rng,
+    budget: Budget,
+    heuristic: HeuristicAgent,
+}
+
+/// UCB1の探索の強さ。
+const EXPLORATION: f64 = 0.7;
+
+impl IsmctsAgent {
+    pub fn new(seed: u64, budget: Budget) -> Self {
+        IsmctsAgent {
+            rng: rand_chacha::ChaCha8Rng::seed_from_u64(seed),
+            budget,
+            heuristic: HeuristicAgent::new(),
+        }
+    }
+}
+
+/// `view`の席から見た、まだ場所が分からないカードについての観測。
+struct Observation {
+    me: usize,
+    state: PlayState,
+    /// 自分以外の手札と、ナポレオンでなければ捨て札(最後の要素)の枚数
+    capacity: Vec<usize>,
+    unknown: Vec<Card>,
+    /// `voids[seat][suit]`: 台札に従わなかったのでそのスートを持っていない
+    voids: Vec<[bool; 4]>,
+    aide_card: Card,
+    /// 副官が分かっていれば、その席
+    aide: Option<usize>,
+    /// 終わったトリックの勝者と、取った絵札の枚数
+    won: Vec<(usize, usize)>,
+}
+
+fn suit_index(suit: Suit) -> usize {
+    Suit::ALL.iter().position(|s| *s == suit).unwrap()
+}
+
+impl Observation {
+    fn new(view: &PlayerView) -> Self {
+        let public = &view.public;
+        let seat =
+            |id: &crate::player::Player| public.players.iter().position(|p| p == id).unwrap();
+        let declaration = public.declaration.as_ref().expect("declaration is set");
+        let me = seat(&view.player);
+        let napoleon = seat(&declaration.napoleon);
+        let trump = declaration.suit;
+        let rules = public.rules;
+        let free = rules.free_cards(trump);
+
+        let mut known = view.hands;
+        known.extend(view.discards.iter().flatten().copied());
+        let n = public.players.len();
+        let mut voids = vec![[false; 4]; n];
+        let plays = public
+            .trick_results
+            .iter()
+            .map(|r| r.trick.as_slice())
+            .chain(std::iter::once(public.trick.plays.as_slice()));
+        for trick in plays {
+            let Some(lead) = lead_suit(trick.iter().map(|p| &p.card)) else {
+                continue;
+            };
+            for play in trick {
+                known.insert(play.card);
+                if !play.card.is_suit(lead) && !free.contains(&play.card) {
+                    voids[seat(&play.player)][suit_index(lead)] = true;
+                }
+            }
+        }
+        let unknown = (rules.deck() - known).to_vec();
+
+        let mut capacity = vec![0; n + 1];
+        for (i, n) in public.hand_counts.iter().enumerate() {
+            if i != me {
+                capacity[i] = *n;
+            }
+        }
+        capacity[n] = if view.discards.is_some() {
+            0
+        } else {
+            rules.opens()
+        };
+
+        let leader = match public.trick.plays.first() {
+            Some(p) => seat(&p.player),
+            None => seat(public.next_player.as_ref().expect("it is my turn")),
+        };
+        let mut hands = vec![CardSet::new(); n];
+        hands[me] = view.hands;
+        Observation {
+            me,
+            state: PlayState {
+                hands,
+                trump,
+                rules,
+                number: declaration.number,
+                base_score: declaration.base_score(&rules),
+                napoleon,
+                aide: None,
+                leader,
+                trick: public.trick.plays.iter().map(|p| p.card).collect(),
+                trick_no: public.trick_results.len() as u8 + 1,
+                napoleon_points: 0,
+            },
+            capacity,
+            unknown,
+            voids,
+            aide_card: declaration.aide,
+            aide: public.aide.as_ref().map(seat),
+            won: public
+                .trick_results
+                .iter()
+                .map(|r| (seat(&r.winner), r.face_cards.len()))
+                .collect(),
+        }
+    }
+
+    /// 場所が分からないカードを配る。`voids`を守れなければ守らずに配る。
+    fn deal<R: rand::Rng>(&self, rng: &mut R) -> Vec<CardSet> {
+        let holders = self.capacity.len();
+        let eligible = |card: &Card, holder: usize, strict: bool| {
+            holder == holders - 1
+                || !strict
+                || card.is_joker()
+                || !self.voids[holder][suit_index(card.suit)]
+        };
+        for attempt in 0..50 {
+            let strict = attempt < 49;
+            let mut cards = self.unknown.clone();
+            cards.shuffle(rng);
+            // 持てる席が少ないカードから配る
+            cards.sort_by_key(|c| (0..holders).filter(|h| eligible(c, *h, strict)).count());
+            let mut capacity = self.capacity.clone();
+            let mut hidden = vec![CardSet::new(); holders];
+            let ok = cards.iter().all(|card| {
+                let total: usize = (0..holders)
+                    .filter(|h| eligible(card, *h, strict))
+                    .map(|h| capacity[h])
+                    .sum();
+                if total == 0 {
+                    return false;
+                }
+                let mut k = rng.gen_range(0..total);
+                let holder = (0..holders)
+                    .filter(|h| eligible(card, *h, strict))
+                    .find(|h| {
+                        if k < capacity[*h] {
+                            true
+                        } else {
+                            k -= capacity[*h];
+                            false
+                        }
+                    })
+                    .unwrap();
+                capacity[holder] -= 1;
+                hidden[holder].insert(*card);
+                true
+            });
+            if ok {
+                return hidden;
+            }
+        }
+        unreachable!("cards can always be dealt without voids")
+    }
+
+    /// 観測と矛盾しない全員の手札を1つ選ぶ。
+    fn determinize<R: rand::Rng>(&self, rng: &mut R) -> PlayState {
+        let hidden = self.deal(rng);
+        let mut state = self.state.clone();
+        let players = state.hands.len();
+        for (seat, cards) in hidden.into_iter().take(players).enumerate() {
+            if seat != self.me {
+                state.hands[seat] = cards;
+            }
+        }
+        let holder = self
+            .aide
+            .or_else(|| state.hands.iter().position(|h| h.contains(&self.aide_card)));
+        state.aide = holder.filter(|s| *s != state.napoleon);
+        state.napoleon_points = self
+            .won
+            .iter()
+            .filter(|(winner, _)| state.is_napoleon_side(*winner))
+            .map(|(_, n)| n)
+            .sum();
+        state
+    }
+}
+
+struct Node {
+    card: Option<Card>,
+    /// このカードを出した席
+    seat: usize,
+    children: Vec<usize>,
+    visits: f64,
+    reward: f64,
+    /// 選べる状態で親に来た回数
+    available: f64,
+}
+
+/// 最後まで出した`state`での`seat`の報酬。勝ち負けを主に、取った絵札の枚数も少し見る。
+fn reward(state: &PlayState, seat: usize) -> f64 {
+    let napoleon_win = if state.winner() == Team::Napoleon {
+        1.0
+    } else {
+        0.0
+    };
+    let napoleon = 0.8 * napoleon_win + 0.2 * state.napoleon_points as f64 / 20.0;
+    if state.is_napoleon_side(seat) {
+        napoleon
+    } else {
+        1.0 - napoleon
+    }
+}
+
+impl IsmctsAgent {
+    fn search(&mut self, view: &PlayerView) -> Card {
+        let observation = Observation::new(view);
+        let mut tree = vec![Node {
+            card: None,
+            seat: observation.me,
+            children: Vec::new(),
+            visits: 0.0,
+            reward: 0.0,
+            available: 0.0,
+        }];
+        let start = Instant::now();
+        let mut iteration = 0;
+        loop {
+            match self.budget {
+                Budget::Iterations(n) if iteration >= n => break,
+                Budget::Time(t) if iteration > 0 && start.elapsed() >= t => break,
+                _ => {}
+            }
+            iteration += 1;
+
+            let mut state = observation.determinize(&mut self.rng);
+            let mut path = vec![0];
+            let mut node = 0;
+            // 選択と展開
+            while !state.is_finished() {
+                let legal = state.legal();
+                let tried: CardSet = tree[node]
+                    .children
+                    .iter()
+                    .filter_map(|i| tree[*i].card)
+                    .collect();
+                let seat = state.to_move();
+                if let Some(card) = (legal - tried).iter().choose(&mut self.rng) {
+                    let child = tree.len();
+                    tree.push(Node {
+                        card: Some(card),
+                        seat,
+                        children: Vec::new(),
+                        visits: 0.0,
+                        reward: 0.0,
+                        available: 0.0,
+                    });
+                    tree[node].children.push(child);
+                    state.play(card);
+                    path.push(child);
+                    break;
+                }
+                let children: Vec<usize> = tree[node]
+                    .children
+                    .iter()
+                    .filter(|i| legal.contains(&tree[**i].card.unwrap()))
+                    .cloned()
+                    .collect();
+                for i in children.iter() {
+                    tree[*i].available += 1.0;
+                }
+                let ucb = |n: &Node| {
+                    n.reward / n.visits + EXPLORATION * (n.available.ln() / n.visits).sqrt()
+                };
+                node = *children
+                    .iter()
+                    .max_by(|a, b| ucb(&tree[**a]).total_cmp(&ucb(&tree[**b])))
+                    .unwrap();
+                state.play(tree[node].card.unwrap());
+                path.push(node);
+            }
+            // ランダムに最後まで出す
+            while !state.is_finished() {
+                let card = state.legal().iter().choose(&mut self.rng).unwrap();
+                state.play(card);
+            }
+            for i in path {
+                let seat = tree[i].seat;
+                tree[i].visits += 1.0;
+                tree[i].reward += reward(&state, seat);
+            }
+        }
+        tree[0]
+            .children
+            .iter()
+            .max_by(|a, b| tree[**a].visits.total_cmp(&tree[**b].visits))
+            .and_then(|i| tree[*i].card)
+            .unwrap_or(view.legal_plays[0])
+    }
+}
+
+impl Agent for IsmctsAgent {
+    fn choose_bid(&mut self, view: &PlayerView) -> Option<Bid> {
+        self.heuristic.choose_bid(view)
+    }
+
+    fn choose_declaration(&mut self, view: &PlayerView) -> Declaration {
+        self.heuristic.choose_declaration(view)
+    }
+
+    fn choose_discards(&mut self, view: &PlayerView) -> Vec<Card> {
+        self.heuristic.choose_discards(view)
+    }
+
+    fn choose_play(&mut self, view: &PlayerView) -> Card {
+        if view.legal_plays.len() == 1 {
+            return view.legal_plays[0];
+        }
+        let card = self.search(view);
+        debug_assert!(view.legal_plays.contains(&card));
+        card
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{play_round, RandomAgent};
+    use crate::player::Players;
+    use crate::round::Round;
+    use crate::settlement::Settlement;
+    use crate::trick::Play;
+
+    fn started_round(seed: u64, n: usize) -> anyhow::Result<Round> {
+        let players = Players::default();
+        let mut round = Round::new_with_seed(players.clone(), seed)?;
+        let aide = round.field_players.0[2].hands.to_vec()[0];
+        round.set_declaration(Declaration::new(
+            players.0[0].clone(),
+            Some(Suit::Spade),
+            13,
+            aide,
+        )?)?;
+        round.exchange(&players.0[0], round.opens.clone())?;
+        for _ in 0..n {
+            round.play(Play::new(
+                round.next_player().unwrap(),
+                round.legal_plays()[0],
+            ))?;
+        }
+        Ok(round)
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(1, 0)]
+    #[case(2, 13)]
+    #[case(3, 31)]
+    fn test_determinize(#[case] seed: u64, #[case] n: usize) -> anyhow::Result<()> {
+        let round = started_round(seed, n)?;
+        let player = round.next_player().unwrap();
+        let view = PlayerView::new(&round, &player)?;
+        let observation = Observation::new(&view);
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(seed);
+        for _ in 0..20 {
+            let state = observation.determinize(&mut rng);
+            // 自分の手札はそのままで、枚数は実際と同じ
+            assert_eq!(state.hands[observation.me], view.hands);
+            for (hands, n) in state.hands.iter().zip(view.public.hand_counts.iter()) {
+                assert_eq!(hands.len(), *n);
+            }
+            // 台札に従わなかったスートは持っていない
+            for (seat, voids) in observation.voids.iter().enumerate() {
+                for (suit, void) in Suit::ALL.iter().zip(voids.iter()) {
+                    if *void {
+                        assert!(state.hands[seat].iter().all(|c| !c.is_suit(*suit)));
+                    }
+                }
+            }
+            let actual = PlayState::from_round(&round)?;
+            assert_eq!(state.to_move(), actual.to_move());
+            assert_eq!(state.trick, actual.trick);
+            assert_eq!(state.legal().to_vec(), view.legal_plays);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_deterministic() -> anyhow::Result<()> {
+        let round = started_round(4, 7)?;
+        let player = round.next_player().unwrap();
+        let view = PlayerView::new(&round, &player)?;
+        let a = IsmctsAgent::new(1, Budget::Iterations(100)).choose_play(&view);
+        let b = IsmctsAgent::new(1, Budget::Iterations(100)).choose_play(&view);
+        assert_eq!(a, b);
+        assert!(view.legal_plays.contains(&a));
+
+        let c = IsmctsAgent::new(1, Budget::Time(Duration::from_millis(10))).choose_play(&view);
+        assert!(view.legal_plays.contains(&c));
+        Ok(())
+    }
+
+    #[test]
+    fn test_beats_random() -> anyhow::Result<()> {
+        let mut total = 0;
+        for seed in 0..20 {
+            let seat = (seed % 5) as usize;
+            let mut agents: Vec<Box<dyn Agent>> = (0..5)
+                .map(|i| Box::new(RandomAgent::new(seed * 5 + i)) as Box<dyn Agent>)
+                .collect();
+            agents[seat] = Box::new(IsmctsAgent::new(seed, Budget::Iterations(100)));
+            let mut round = Round::new_with_seed(Players::default(), seed)?;
+            play_round(&mut round, &mut agents)?;
+            let player = &round.field_players.0[seat].player;
+            total += Settlement::new(&round)?.delta(player).unwrap();
+        }
+        assert!(total > 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+use rand::seq::{IteratorRandom as _, SliceRandom as _};
+use rand::{Rng as _, SeedableRng as _};
+
+use super::Agent;
+use crate::bidding::Bid;
+use crate::card::Card;
+use crate::declaration::Declaration;
+use crate::view::PlayerView;
+
+/// ルールで許される操作から一様に選ぶ。競りでは半分の確率でパスする。
+pub struct RandomAgent {
+    rng: rand_chacha::ChaCha8Rng,
+}
+
+impl RandomAgent {
+    pub fn new(seed: u64) -> Self {
+        RandomAgent {
+            rng: rand_chacha::ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Agent for RandomAgent {
+    fn choose_bid(&mut self, view: &PlayerView) -> Option<Bid> {
+        if self.rng.gen_bool(0.5) {
+            return None;
+        }
+        let highest = view.public.highest_bid().map(|(_, b)| b);
+        Bid::all()
+            .filter(|b| b.number >= view.public.rules.min_bid && highest.is_none_or(|h| *b > h))
+            .choose(&mut self.rng)
+    }
+
+    fn choose_declaration(&mut self, view: &PlayerView) -> Declaration {
+        let (_, bid) = view.public.highest_bid().expect("bidding is decided");
+        let aide = Card::deck(view.public.rules.joker)
+            .filter(|c| !view.hands.contains(c))
+            .choose(&mut self.rng)
+            .unwrap();
+        Declaration::with_rules(
+            view.player.clone(),
+            bid.suit,
+            bid.number,
+            aide,
+            &view.public.rules,
+        )
+        .unwrap()
+    }
+
+    fn choose_discards(&mut self, view: &PlayerView) -> Vec<Card> {
+        let cards: Vec<Card> = view
+            .hands
+            .iter()
+            .chain(view.opens.iter().flatten().copied())
+            .collect();
+        cards
+            .choose_multiple(&mut self.rng, view.public.rules.opens())
+            .cloned()
+            .collect()
+    }
+
+    fn choose_play(&mut self, view: &PlayerView) -> Card {
+        *view.legal_plays.choose(&mut self.rng).unwrap()
+    }
+}
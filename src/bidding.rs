@@ -0,0 +1,364 @@
+use crate::card::{Card, Suit};
+use crate::declaration::Declaration;
+use crate::error::{Error, Result};
+use crate::player::{Player, Players};
+use crate::rule::RuleSet;
+
+/// 立ち。枚数が多いほど強く、同じ枚数ならクラブ < ダイヤ < ハート < スペード < 切り札なしの順に強い。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Bid {
+    pub number: usize,
+    pub suit: Option<Suit>,
+}
+
+impl Bid {
+    /// 人数によらず宣言できる枚数。人数ごとの下限は`RuleSet::min_bid`で決まる。
+    pub const NUMBERS: std::ops::RangeInclusive<usize> = 12..=20;
+
+    pub fn new(number: usize, suit: Option<Suit>) -> Result<Self> {
+        if !Self::NUMBERS.contains(&number) {
+            return Err(Error::InvalidDeclaration(number));
+        }
+        Ok(Bid { number, suit })
+    }
+
+    /// 宣言できる全ての立ちを弱い順に。
+    pub fn all() -> impl Iterator<Item = Bid> {
+        Self::NUMBERS.flat_map(|number| {
+            [
+                Some(Suit::Club),
+                Some(Suit::Diamond),
+                Some(Suit::Heart),
+                Some(Suit::Spade),
+                None,
+            ]
+            .map(|suit| Bid { number, suit })
+        })
+    }
+
+    fn key(&self) -> (usize, u8) {
+        let suit = match self.suit {
+            Some(Suit::Club) => 0,
+            Some(Suit::Diamond) => 1,
+            Some(Suit::Heart) => 2,
+            Some(Suit::Spade) => 3,
+            None => 4,
+        };
+        (self.number, suit)
+    }
+}
+
+/// 枚数とスートを続けて`13S`のように書く。切り札なしは`16NT`。
+impl std::fmt::Display for Bid {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.number)?;
+        match self.suit {
+            Some(suit) => suit.fmt(f),
+            None => f.write_str("NT"),
+        }
+    }
+}
+
+impl std::str::FromStr for Bid {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidNotation(s.to_string());
+        let s = s.trim();
+        let i = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+        let number = s[..i].parse().map_err(|_| invalid())?;
+        let suit = match &s[i..] {
+            "NT" | "nt" => None,
+            suit => Some(suit.parse().map_err(|_| invalid())?),
+        };
+        Bid::new(number, suit)
+    }
+}
+
+impl PartialOrd for Bid {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Bid {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Outcome {
+    /// 全員パスしたので配り直し
+    Redeal,
+    Napoleon {
+        player: Player,
+        bid: Bid,
+    },
+}
+
+/// 席順に立ちを宣言していき、ナポレオンを決める。パスしたプレイヤーは以降宣言できない。
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Bidding {
+    players: Players,
+    rules: RuleSet,
+    turn: usize,
+    passed: Vec<bool>,
+    bids: Vec<(Player, Option<Bid>)>,
+    highest: Option<(usize, Bid)>,
+}
+
+impl Bidding {
+    /// `RuleSet::default()`に従って競る。
+    pub fn new(players: Players) -> Self {
+        Self::with_rules(players, RuleSet::default())
+    }
+
+    /// `rules`に従って競る。`RuleSet::bid_numbers`にない立ちは宣言できない。
+    pub fn with_rules(players: Players, rules: RuleSet) -> Self {
+        Bidding {
+            passed: vec![false; players.0.len()],
+            players,
+            rules,
+            turn: 0,
+            bids: Vec::new(),
+            highest: None,
+        }
+    }
+
+    /// これまでの宣言。`None`はパス。
+    pub fn bids(&self) -> &[(Player, Option<Bid>)] {
+        &self.bids
+    }
+
+    pub fn highest(&self) -> Option<(&Player, Bid)> {
+        self.highest.map(|(i, b)| (&self.players.0[i], b))
+    }
+
+    pub fn next_player(&self) -> Option<&Player> {
+        if self.outcome().is_some() {
+            return None;
+        }
+        Some(&self.players.0[self.turn])
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        let n = self.passed.len();
+        let n_passed = self.passed.iter().filter(|p| **p).count();
+        match self.highest {
+            None if n_passed == n => Some(Outcome::Redeal),
+            Some((i, bid)) if n_passed == n - 1 => Some(Outcome::Napoleon {
+                player: self.players.0[i].clone(),
+                bid,
+            }),
+            _ => None,
+        }
+    }
+
+    fn ensure_turn(&self, player: &Player) -> Result<()> {
+        let expected = self.next_player().ok_or(Error::BiddingFinished)?;
+        if expected != player {
+            return Err(Error::OutOfTurn {
+                expected: expected.clone(),
+                actual: player.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    fn advance(&mut self) {
+        let n = self.passed.len();
+        for _ in 0..n {
+            self.turn = (self.turn + 1) % n;
+            if !self.passed[self.turn] {
+                return;
+            }
+        }
+    }
+
+    pub fn bid(&mut self, player: &Player, bid: Bid) -> Result<()> {
+        self.ensure_turn(player)?;
+        if !self.rules.bid_numbers().contains(&bid.number) {
+            return Err(Error::InvalidDeclaration(bid.number));
+        }
+        if let Some((_, highest)) = self.highest {
+            if bid <= highest {
+                return Err(Error::BidTooLow { highest });
+            }
+        }
+        self.highest = Some((self.turn, bid));
+        self.bids.push((player.clone(), Some(bid)));
+        self.advance();
+        Ok(())
+    }
+
+    pub fn pass(&mut self, player: &Player) -> Result<()> {
+        self.ensure_turn(player)?;
+        self.passed[self.turn] = true;
+        self.bids.push((player.clone(), None));
+        self.advance();
+        Ok(())
+    }
+
+    /// 落札したナポレオンが副官を指名して、`Round::set_declaration`に渡す宣言を作る。
+    pub fn declaration(&self, aide: Card) -> Result<Declaration> {
+        match self.outcome() {
+            Some(Outcome::Napoleon { player, bid }) => {
+                Declaration::with_rules(player, bid.suit, bid.number, aide, &self.rules)
+            }
+            _ => Err(Error::NapoleonNotDecided),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rstest::rstest]
+    #[test]
+    #[case(Bid { number: 14, suit: Some(Suit::Club) }, Bid { number: 13, suit: None }, true)]
+    #[case(Bid { number: 13, suit: Some(Suit::Spade) }, Bid { number: 13, suit: Some(Suit::Heart) }, true)]
+    #[case(Bid { number: 13, suit: None }, Bid { number: 13, suit: Some(Suit::Spade) }, true)]
+    #[case(Bid { number: 13, suit: Some(Suit::Club) }, Bid { number: 13, suit: Some(Suit::Diamond) }, false)]
+    #[case(Bid { number: 13, suit: Some(Suit::Club) }, Bid { number: 13, suit: Some(Suit::Club) }, false)]
+    fn test_bid_order(#[case] a: Bid, #[case] b: Bid, #[case] greater: bool) {
+        assert_eq!(a > b, greater);
+    }
+
+    #[test]
+    fn test_bid_all() {
+        let bids: Vec<Bid> = Bid::all().collect();
+        assert_eq!(bids.len(), 45);
+        assert!(bids.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_bid_new() {
+        assert!(Bid::new(13, None).is_ok());
+        assert!(Bid::new(12, None).is_ok());
+        assert_eq!(Bid::new(11, None), Err(Error::InvalidDeclaration(11)));
+        assert_eq!(Bid::new(21, None), Err(Error::InvalidDeclaration(21)));
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case("13S", Bid { number: 13, suit: Some(Suit::Spade) })]
+    #[case("20C", Bid { number: 20, suit: Some(Suit::Club) })]
+    #[case("16NT", Bid { number: 16, suit: None })]
+    fn test_bid_notation(#[case] s: &str, #[case] bid: Bid) -> anyhow::Result<()> {
+        assert_eq!(bid.to_string(), s);
+        assert_eq!(s.parse::<Bid>()?, bid);
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case("S13")]
+    #[case("13")]
+    #[case("13X")]
+    fn test_bid_notation_invalid(#[case] s: &str) {
+        assert_eq!(s.parse::<Bid>(), Err(Error::InvalidNotation(s.to_string())));
+    }
+
+    #[test]
+    fn test_bidding() -> anyhow::Result<()> {
+        let players = Players::default();
+        let p = &players.0;
+        let mut b = Bidding::new(players.clone());
+        let bid13 = Bid::new(13, Some(Suit::Heart))?;
+        let bid14 = Bid::new(14, Some(Suit::Club))?;
+
+        assert_eq!(
+            b.bid(&p[1], bid13),
+            Err(Error::OutOfTurn {
+                expected: p[0].clone(),
+                actual: p[1].clone(),
+            })
+        );
+        b.bid(&p[0], bid13)?;
+        assert_eq!(
+            b.bid(&p[1], bid13),
+            Err(Error::BidTooLow { highest: bid13 })
+        );
+        b.pass(&p[1])?;
+        b.bid(&p[2], bid14)?;
+        b.pass(&p[3])?;
+        b.pass(&p[4])?;
+        assert!(b.outcome().is_none());
+        // パスしたプレイヤーは飛ばされる
+        assert_eq!(b.next_player(), Some(&p[0]));
+        b.pass(&p[0])?;
+
+        assert_eq!(
+            b.outcome(),
+            Some(Outcome::Napoleon {
+                player: p[2].clone(),
+                bid: bid14,
+            })
+        );
+        assert_eq!(b.next_player(), None);
+        assert_eq!(b.pass(&p[2]), Err(Error::BiddingFinished));
+        assert_eq!(b.bids().len(), 6);
+
+        let d = b.declaration(Card::try_from(1)?)?;
+        assert_eq!(d.napoleon, p[2]);
+        assert_eq!(d.number, 14);
+        assert_eq!(d.suit, Some(Suit::Club));
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(RuleSet::default(), 12, false)]
+    #[case(RuleSet::default(), 13, true)]
+    #[case(RuleSet { min_bid: 15, ..Default::default() }, 14, false)]
+    #[case(RuleSet { min_bid: 15, ..Default::default() }, 15, true)]
+    #[case(RuleSet::for_players(6), 12, true)]
+    fn test_bidding_min_bid(
+        #[case] rules: RuleSet,
+        #[case] number: usize,
+        #[case] ok: bool,
+    ) -> anyhow::Result<()> {
+        let players = Players::with_len(rules.players);
+        let mut b = Bidding::with_rules(players.clone(), rules);
+        let bid = Bid::new(number, None)?;
+        assert_eq!(b.bid(&players.0[0], bid).is_ok(), ok);
+        if ok {
+            for p in players.0.iter().skip(1) {
+                b.pass(p)?;
+            }
+            assert_eq!(b.declaration(Card::try_from(1)?)?.number, number);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bidding_redeal() -> anyhow::Result<()> {
+        let players = Players::default();
+        let mut b = Bidding::new(players.clone());
+        for p in players.0.iter() {
+            b.pass(p)?;
+        }
+        assert_eq!(b.outcome(), Some(Outcome::Redeal));
+        assert_eq!(
+            b.declaration(Card::try_from(1)?),
+            Err(Error::NapoleonNotDecided)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bidding_round() -> anyhow::Result<()> {
+        let players = Players::default();
+        let mut round = crate::round::Round::new(players.clone())?;
+        let mut b = Bidding::new(players.clone());
+        b.bid(&players.0[0], Bid::new(16, Some(Suit::Spade))?)?;
+        for p in players.0.iter().skip(1) {
+            b.pass(p)?;
+        }
+        round.set_declaration(b.declaration(round.field_players.0[1].hands.to_vec()[0])?)?;
+        assert_eq!(round.phase(), crate::round::Phase::Exchange);
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use crate::error::{Error, Result};
+
 #[derive(
     Debug, PartialEq, Eq, Clone, Copy, Hash, serde::Serialize, serde::Deserialize, Default,
 )]
@@ -10,6 +12,8 @@ pub enum Suit {
 }
 
 impl Suit {
+    pub const ALL: [Suit; 4] = [Suit::Spade, Suit::Heart, Suit::Diamond, Suit::Club];
+
     pub fn reverse(&self) -> Self {
         match self {
             Suit::Spade => Suit::Club,
@@ -20,14 +24,157 @@ impl Suit {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+/// `S`, `H`, `D`, `C`。`{:#}`では`♠`, `♥`, `♦`, `♣`。
+impl std::fmt::Display for Suit {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let s = match (self, f.alternate()) {
+            (Suit::Spade, false) => "S",
+            (Suit::Heart, false) => "H",
+            (Suit::Diamond, false) => "D",
+            (Suit::Club, false) => "C",
+            (Suit::Spade, true) => "♠",
+            (Suit::Heart, true) => "♥",
+            (Suit::Diamond, true) => "♦",
+            (Suit::Club, true) => "♣",
+        };
+        f.write_str(s)
+    }
+}
+
+impl std::str::FromStr for Suit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "S" | "s" | "♠" | "♤" => Ok(Suit::Spade),
+            "H" | "h" | "♥" | "♡" => Ok(Suit::Heart),
+            "D" | "d" | "♦" | "♢" => Ok(Suit::Diamond),
+            "C" | "c" | "♣" | "♧" => Ok(Suit::Club),
+            _ => Err(Error::InvalidNotation(s.to_string())),
+        }
+    }
+}
+
+/// `number`は1(A)から13(K)。ジョーカーは`Card::JOKER`で、`number`が0になる。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Card {
     pub number: u8,
     pub suit: Suit,
 }
 
+/// スートと数字を続けて`SA`, `HQ`, `D10`のように書く。`{:#}`では`♠A`のように書く。
+/// ジョーカーは`JK`、`{:#}`では`🃏`。
+impl std::fmt::Display for Card {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.is_joker() {
+            return f.write_str(if f.alternate() { "🃏" } else { "JK" });
+        }
+        self.suit.fmt(f)?;
+        match self.number {
+            1 => f.write_str("A"),
+            11 => f.write_str("J"),
+            12 => f.write_str("Q"),
+            13 => f.write_str("K"),
+            n => write!(f, "{}", n),
+        }
+    }
+}
+
+impl std::str::FromStr for Card {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidNotation(s.to_string());
+        if matches!(s.trim(), "JK" | "jk" | "🃏") {
+            return Ok(Card::JOKER);
+        }
+        let mut chars = s.trim().chars();
+        let suit: Suit = chars
+            .next()
+            .ok_or_else(invalid)?
+            .to_string()
+            .parse()
+            .map_err(|_| invalid())?;
+        // `S05`や`S+5`は書いた通りに戻らないので読まない
+        let number = match chars.as_str().to_ascii_uppercase().as_str() {
+            "A" => 1,
+            "2" => 2,
+            "3" => 3,
+            "4" => 4,
+            "5" => 5,
+            "6" => 6,
+            "7" => 7,
+            "8" => 8,
+            "9" => 9,
+            "10" => 10,
+            "J" => 11,
+            "Q" => 12,
+            "K" => 13,
+            _ => return Err(invalid()),
+        };
+        Ok(Card { number, suit })
+    }
+}
+
+/// 手札を空白区切りで書く。
+pub fn format_hands(hands: &[Card]) -> String {
+    hands
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// 空白区切りの手札を読む。
+pub fn parse_hands(s: &str) -> Result<Hands> {
+    s.split_whitespace().map(|c| c.parse()).collect()
+}
+
+/// 番号の代わりに`SA`のような文字列で(デ)シリアライズする。`#[serde(with = "napo::card::text")]`で使う。
+pub mod text {
+    use super::Card;
+
+    pub fn serialize<S>(card: &Card, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(card)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Card, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        serde::Deserialize::deserialize(deserializer)
+    }
+
+    /// 手札のようなカードの列を文字列の配列で(デ)シリアライズする。
+    pub mod cards {
+        use super::Card;
+        use serde::ser::SerializeSeq;
+
+        pub fn serialize<S>(cards: &[Card], serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            let mut seq = serializer.serialize_seq(Some(cards.len()))?;
+            for c in cards {
+                seq.serialize_element(&c.to_string())?;
+            }
+            seq.end()
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Card>, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            serde::Deserialize::deserialize(deserializer)
+        }
+    }
+}
+
 impl serde::Serialize for Card {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
         S: serde::ser::Serializer,
     {
@@ -40,38 +187,63 @@ struct CardVisitor;
 impl serde::de::Visitor<'_> for CardVisitor {
     type Value = Card;
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        formatter.write_str("an integer between 1 and 52")
+        formatter.write_str("an integer between 1 and 53 or a card notation")
     }
 
-    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    fn visit_str<E>(self, value: &str) -> std::result::Result<Self::Value, E>
     where
         E: serde::de::Error,
     {
-        Card::try_from(value as u8).map_err(|e| E::custom(e))
+        value
+            .parse()
+            .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(value), &self))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> std::result::Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        u8::try_from(value)
+            .ok()
+            .and_then(|id| Card::try_from(id).ok())
+            .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Unsigned(value), &self))
     }
 }
 
 impl<'de> serde::Deserialize<'de> for Card {
-    fn deserialize<D>(deserializer: D) -> Result<Card, D::Error>
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Card, D::Error>
     where
         D: serde::de::Deserializer<'de>,
     {
-        deserializer.deserialize_u8(CardVisitor)
+        // 自分で型を持たない形式では`deserialize_any`が使えないので、書いたときの番号で読む
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(CardVisitor)
+        } else {
+            deserializer.deserialize_u8(CardVisitor)
+        }
     }
 }
 
+/// ジョーカーの番号。52枚の後に続く。
+const JOKER_ID: u8 = 53;
+
 impl TryFrom<u8> for Card {
-    type Error = anyhow::Error;
+    type Error = Error;
 
-    fn try_from(id: u8) -> anyhow::Result<Self> {
-        anyhow::ensure!((1..=52).contains(&id), "invalid id \"{}\"", id);
+    fn try_from(id: u8) -> Result<Self> {
+        if id == JOKER_ID {
+            return Ok(Card::JOKER);
+        }
+        if !(1..=52).contains(&id) {
+            return Err(Error::InvalidCardId(id));
+        }
         let number = ((id - 1) % 13) + 1;
         let suit = match (id - 1) / 13 {
             0 => Suit::Spade,
             1 => Suit::Heart,
             2 => Suit::Diamond,
             3 => Suit::Club,
-            _ => anyhow::bail!("invalid id \"{}\"", id),
+            _ => return Err(Error::InvalidCardId(id)),
         };
         Ok(Card { number, suit })
     }
@@ -79,6 +251,9 @@ impl TryFrom<u8> for Card {
 
 impl From<Card> for u8 {
     fn from(card: Card) -> Self {
+        if card.is_joker() {
+            return JOKER_ID;
+        }
         let suit_num: u8 = match card.suit {
             Suit::Spade => 0,
             Suit::Heart => 1,
@@ -90,6 +265,30 @@ impl From<Card> for u8 {
 }
 
 impl Card {
+    pub const JOKER: Card = Card {
+        number: 0,
+        suit: Suit::Spade,
+    };
+
+    /// 52枚を番号の順に。
+    pub fn all() -> impl Iterator<Item = Card> {
+        (1..=52).map(|id| Card::try_from(id).unwrap())
+    }
+
+    /// 52枚に、`joker`ならジョーカーを加えて番号の順に。
+    pub fn deck(joker: bool) -> impl Iterator<Item = Card> {
+        Self::all().chain(joker.then_some(Card::JOKER))
+    }
+
+    pub fn is_joker(&self) -> bool {
+        *self == Card::JOKER
+    }
+
+    /// `suit`のカードかどうか。ジョーカーはどのスートでもない。
+    pub fn is_suit(&self, suit: Suit) -> bool {
+        !self.is_joker() && self.suit == suit
+    }
+
     pub fn is_almighty(&self) -> bool {
         (self.number == 1) && (self.suit == Suit::Spade)
     }
@@ -101,9 +300,23 @@ impl Card {
     pub fn is_face(&self) -> bool {
         (self.number == 1) || (self.number >= 10)
     }
+
+    /// 同じスート内での強さ。Aが最も強く、2が最も弱い。
+    pub fn rank(&self) -> u8 {
+        if self.number == 1 {
+            14
+        } else {
+            self.number
+        }
+    }
 }
 
-pub type Hands = [Card; 10];
+pub type Hands = Vec<Card>;
+
+/// 出した順に並べた`cards`の台札のスート。ジョーカーから出したときは、次に出たカードのスートになる。
+pub fn lead_suit<'a>(cards: impl IntoIterator<Item = &'a Card>) -> Option<Suit> {
+    cards.into_iter().find(|c| !c.is_joker()).map(|c| c.suit)
+}
 
 #[cfg(test)]
 mod tests {
@@ -127,8 +340,8 @@ mod tests {
 
     #[test]
     fn test_trump_from_id_error() -> anyhow::Result<()> {
-        let t = Card::try_from(53);
-        assert!(t.is_err());
+        let t = Card::try_from(54);
+        assert_eq!(t, Err(Error::InvalidCardId(54)));
         Ok(())
     }
 
@@ -137,8 +350,11 @@ mod tests {
     #[case(Card { number: 1, suit: Suit::Spade }, 1)]
     #[case(Card { number: 10, suit: Suit::Club }, 49)]
     #[case(Card { number: 13, suit: Suit::Diamond }, 39)]
-    fn test_trump_to_id(#[case] card: Card, #[case] id: u8) {
+    #[case(Card::JOKER, 53)]
+    fn test_trump_to_id(#[case] card: Card, #[case] id: u8) -> anyhow::Result<()> {
         assert_eq!(u8::from(card), id);
+        assert_eq!(Card::try_from(id)?, card);
+        Ok(())
     }
 
     #[rstest::rstest]
@@ -163,10 +379,29 @@ mod tests {
     #[case(Card { number: 10, suit: Suit::Club }, true)]
     #[case(Card { number: 13, suit: Suit::Diamond }, true)]
     #[case(Card { number: 2, suit: Suit::Spade }, false)]
+    #[case(Card::JOKER, false)]
     fn test_is_face(#[case] card: Card, #[case] is_face: bool) {
         assert_eq!(card.is_face(), is_face);
     }
 
+    #[rstest::rstest]
+    #[test]
+    #[case(Card { number: 1, suit: Suit::Spade }, Suit::Spade, true)]
+    #[case(Card { number: 1, suit: Suit::Spade }, Suit::Heart, false)]
+    #[case(Card::JOKER, Suit::Spade, false)]
+    fn test_is_suit(#[case] card: Card, #[case] suit: Suit, #[case] is_suit: bool) {
+        assert_eq!(card.is_suit(suit), is_suit);
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(Card { number: 1, suit: Suit::Heart }, 14)]
+    #[case(Card { number: 13, suit: Suit::Heart }, 13)]
+    #[case(Card { number: 2, suit: Suit::Heart }, 2)]
+    fn test_rank(#[case] card: Card, #[case] rank: u8) {
+        assert_eq!(card.rank(), rank);
+    }
+
     #[rstest::rstest]
     #[test]
     #[case(Card { number: 2, suit: Suit::Heart }, "15")]
@@ -174,6 +409,155 @@ mod tests {
         assert_eq!(serde_json::to_string(&card).unwrap(), json);
     }
 
+    #[rstest::rstest]
+    #[test]
+    #[case(Card { number: 1, suit: Suit::Spade }, "SA", "♠A")]
+    #[case(Card { number: 12, suit: Suit::Heart }, "HQ", "♥Q")]
+    #[case(Card { number: 10, suit: Suit::Diamond }, "D10", "♦10")]
+    #[case(Card { number: 2, suit: Suit::Club }, "C2", "♣2")]
+    #[case(Card::JOKER, "JK", "🃏")]
+    fn test_card_notation(#[case] card: Card, #[case] text: &str, #[case] unicode: &str) {
+        assert_eq!(card.to_string(), text);
+        assert_eq!(format!("{:#}", card), unicode);
+        assert_eq!(text.parse::<Card>(), Ok(card));
+        assert_eq!(unicode.parse::<Card>(), Ok(card));
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case("")]
+    #[case("S")]
+    #[case("S14")]
+    #[case("X3")]
+    #[case("10")]
+    #[case("J")]
+    #[case("S0")]
+    #[case("S+5")]
+    #[case("S05")]
+    #[case("ST")]
+    #[case("S")]
+    fn test_card_notation_error(#[case] text: &str) {
+        assert_eq!(
+            text.parse::<Card>(),
+            Err(Error::InvalidNotation(text.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_hands_notation() -> anyhow::Result<()> {
+        let hands = parse_hands("SA hq ♦10 C2")?;
+        assert_eq!(
+            hands,
+            vec![
+                Card::try_from(1)?,
+                Card::try_from(25)?,
+                Card::try_from(36)?,
+                Card::try_from(41)?,
+            ]
+        );
+        assert_eq!(format_hands(&hands), "SA HQ D10 C2");
+        assert!(parse_hands("SA X").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_serde() -> anyhow::Result<()> {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Text {
+            #[serde(with = "text")]
+            card: Card,
+            #[serde(with = "text::cards")]
+            hands: Vec<Card>,
+        }
+        let t = Text {
+            card: Card::try_from(1)?,
+            hands: vec![Card::try_from(25)?, Card::try_from(36)?],
+        };
+        let json = serde_json::to_string(&t)?;
+        assert_eq!(json, r#"{"card":"SA","hands":["HQ","D10"]}"#);
+        let t: Text = serde_json::from_str(&json)?;
+        assert_eq!(t.card, Card::try_from(1)?);
+        // 番号でも文字列でも読める
+        assert_eq!(
+            serde_json::from_str::<Card>(r#""HQ""#)?,
+            Card::try_from(25)?
+        );
+        assert_eq!(serde_json::from_str::<Card>("25")?, Card::try_from(25)?);
+        assert_eq!(serde_json::from_str::<Card>(r#""JK""#)?, Card::JOKER);
+        Ok(())
+    }
+
+    /// bincodeのように`deserialize_any`を使えない形式の代わり。
+    struct U8Only(u8);
+
+    impl<'de> serde::Deserializer<'de> for U8Only {
+        type Error = serde::de::value::Error;
+
+        fn deserialize_any<V: serde::de::Visitor<'de>>(
+            self,
+            _: V,
+        ) -> std::result::Result<V::Value, Self::Error> {
+            Err(serde::de::Error::custom("deserialize_any is not supported"))
+        }
+
+        fn deserialize_u8<V: serde::de::Visitor<'de>>(
+            self,
+            visitor: V,
+        ) -> std::result::Result<V::Value, Self::Error> {
+            visitor.visit_u8(self.0)
+        }
+
+        fn is_human_readable(&self) -> bool {
+            false
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u16 u32 u64 u128 f32 f64 char str string bytes
+            byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct
+            map struct enum identifier ignored_any
+        }
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(1)]
+    #[case(52)]
+    #[case(53)]
+    fn test_deserialize_not_self_describing(#[case] id: u8) -> anyhow::Result<()> {
+        use serde::Deserialize;
+
+        assert_eq!(Card::deserialize(U8Only(id))?, Card::try_from(id)?);
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case("0")]
+    #[case("54")]
+    #[case("300")]
+    fn test_json_to_invalid_card(#[case] json: &str) {
+        assert!(serde_json::from_str::<Card>(json).is_err());
+    }
+
+    #[test]
+    fn test_deck() {
+        assert_eq!(Card::deck(false).count(), 52);
+        assert_eq!(Card::deck(true).count(), 53);
+        assert_eq!(Card::deck(true).filter(|c| c.is_face()).count(), 20);
+    }
+
+    #[rstest::rstest]
+    #[test]
+    // ジョーカーから出せば次のカードのスートが台札になる
+    #[case("SA H3", Some(Suit::Spade))]
+    #[case("JK H3 SA", Some(Suit::Heart))]
+    #[case("JK", None)]
+    #[case("", None)]
+    fn test_lead_suit(#[case] cards: &str, #[case] lead: Option<Suit>) -> anyhow::Result<()> {
+        assert_eq!(lead_suit(&parse_hands(cards)?), lead);
+        Ok(())
+    }
+
     #[rstest::rstest]
     #[test]
     #[case("[1,2,30,4,52]", [Card { number: 1, suit: Suit::Spade }, Card { number: 2, suit: Suit::Spade }, Card { number: 4, suit: Suit::Diamond }, Card { number: 4, suit: Suit::Spade }, Card { number: 13, suit: Suit::Club }])]
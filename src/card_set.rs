@@ -0,0 +1,357 @@
+//! 64ビットで表したカードの集合。
+//!
+//! スートごとに13ビットを`2, 3, ..., K, A`の順に並べているので、小さいビットから読むと弱い順になる。
+//! ジョーカーは4つのスートの後の52ビット目。
+use crate::card::{Card, Suit};
+use crate::error::{Error, Result};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CardSet(u64);
+
+const SUIT_MASK: u64 = (1 << 13) - 1;
+/// スートの中の10, J, Q, K, A
+const FACE_MASK: u64 = 0b11111 << 8;
+
+fn suit_index(suit: Suit) -> u32 {
+    match suit {
+        Suit::Spade => 0,
+        Suit::Heart => 1,
+        Suit::Diamond => 2,
+        Suit::Club => 3,
+    }
+}
+
+const JOKER_BIT: u32 = 52;
+
+/// `card`のビット。`number`が1から13にないカードはどこにも当たらず0になる。
+fn mask(card: &Card) -> u64 {
+    if card.is_joker() {
+        return 1 << JOKER_BIT;
+    }
+    if !(1..=13).contains(&card.number) {
+        return 0;
+    }
+    1 << (suit_index(card.suit) * 13 + card.rank() as u32 - 2)
+}
+
+fn card(bit: u32) -> Card {
+    if bit == JOKER_BIT {
+        return Card::JOKER;
+    }
+    let rank = (bit % 13) as u8 + 2;
+    Card {
+        number: if rank == 14 { 1 } else { rank },
+        suit: Suit::ALL[(bit / 13) as usize],
+    }
+}
+
+impl CardSet {
+    pub const EMPTY: CardSet = CardSet(0);
+    /// 52枚全て
+    pub const ALL: CardSet = CardSet((1 << 52) - 1);
+    pub const JOKER: CardSet = CardSet(1 << JOKER_BIT);
+    /// 20枚の絵札
+    pub const FACES: CardSet =
+        CardSet(FACE_MASK | FACE_MASK << 13 | FACE_MASK << 26 | FACE_MASK << 39);
+
+    pub fn new() -> Self {
+        Self::EMPTY
+    }
+
+    /// `suit`の13枚。
+    pub fn suit(suit: Suit) -> Self {
+        CardSet(SUIT_MASK << (suit_index(suit) * 13))
+    }
+
+    pub fn bits(&self) -> u64 {
+        self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(&self, card: &Card) -> bool {
+        self.0 & mask(card) != 0
+    }
+
+    /// `card`を加える。既にあるか、正しいカードでなければ`false`を返す。
+    pub fn insert(&mut self, card: Card) -> bool {
+        let mask = mask(&card);
+        let had = self.0 & mask != 0;
+        self.0 |= mask;
+        mask != 0 && !had
+    }
+
+    /// `card`を除く。なければ`false`を返す。
+    pub fn remove(&mut self, card: &Card) -> bool {
+        let had = self.contains(card);
+        self.0 &= !mask(card);
+        had
+    }
+
+    /// この中の`suit`のカード。
+    pub fn of_suit(&self, suit: Suit) -> Self {
+        *self & Self::suit(suit)
+    }
+
+    /// この中の絵札。
+    pub fn faces(&self) -> Self {
+        *self & Self::FACES
+    }
+
+    /// スートの順に、スートの中では弱い順に。
+    pub fn iter(&self) -> Iter {
+        Iter(self.0)
+    }
+
+    /// 並びの最後のカード。1つのスートの中なら一番強いカード。
+    pub fn last(&self) -> Option<Card> {
+        (self.0 != 0).then(|| card(63 - self.0.leading_zeros()))
+    }
+
+    pub fn to_vec(&self) -> Vec<Card> {
+        self.iter().collect()
+    }
+}
+
+pub struct Iter(u64);
+
+impl Iterator for Iter {
+    type Item = Card;
+
+    fn next(&mut self) -> Option<Card> {
+        if self.0 == 0 {
+            return None;
+        }
+        let b = self.0.trailing_zeros();
+        self.0 &= self.0 - 1;
+        Some(card(b))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.0.count_ones() as usize;
+        (n, Some(n))
+    }
+}
+
+impl ExactSizeIterator for Iter {}
+
+impl IntoIterator for CardSet {
+    type Item = Card;
+    type IntoIter = Iter;
+
+    fn into_iter(self) -> Iter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for &CardSet {
+    type Item = Card;
+    type IntoIter = Iter;
+
+    fn into_iter(self) -> Iter {
+        self.iter()
+    }
+}
+
+impl FromIterator<Card> for CardSet {
+    fn from_iter<T: IntoIterator<Item = Card>>(iter: T) -> Self {
+        let mut set = CardSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<'a> FromIterator<&'a Card> for CardSet {
+    fn from_iter<T: IntoIterator<Item = &'a Card>>(iter: T) -> Self {
+        iter.into_iter().copied().collect()
+    }
+}
+
+impl Extend<Card> for CardSet {
+    fn extend<T: IntoIterator<Item = Card>>(&mut self, iter: T) {
+        for c in iter {
+            self.insert(c);
+        }
+    }
+}
+
+impl std::ops::BitOr for CardSet {
+    type Output = CardSet;
+
+    fn bitor(self, rhs: CardSet) -> CardSet {
+        CardSet(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitAnd for CardSet {
+    type Output = CardSet;
+
+    fn bitand(self, rhs: CardSet) -> CardSet {
+        CardSet(self.0 & rhs.0)
+    }
+}
+
+impl std::ops::Sub for CardSet {
+    type Output = CardSet;
+
+    fn sub(self, rhs: CardSet) -> CardSet {
+        CardSet(self.0 & !rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for CardSet {
+    fn bitor_assign(&mut self, rhs: CardSet) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl std::ops::SubAssign for CardSet {
+    fn sub_assign(&mut self, rhs: CardSet) {
+        self.0 &= !rhs.0;
+    }
+}
+
+/// `format_hands`と同じく空白区切りで書く。
+impl std::fmt::Display for CardSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&crate::card::format_hands(&self.to_vec()))
+    }
+}
+
+impl std::str::FromStr for CardSet {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let cards = crate::card::parse_hands(s)?;
+        let mut set = CardSet::new();
+        if let Some(c) = cards.into_iter().find(|c| !set.insert(*c)) {
+            return Err(Error::DuplicatedCard(c));
+        }
+        Ok(set)
+    }
+}
+
+/// `Vec<Card>`と同じくカードの番号の配列で(デ)シリアライズする。
+impl serde::Serialize for CardSet {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for CardSet {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<CardSet, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        let cards: Vec<Card> = serde::Deserialize::deserialize(deserializer)?;
+        let mut set = CardSet::new();
+        for card in cards {
+            if !set.insert(card) {
+                return Err(serde::de::Error::custom(Error::DuplicatedCard(card)));
+            }
+        }
+        Ok(set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bits() {
+        let all: CardSet = Card::all().collect();
+        assert_eq!(all, CardSet::ALL);
+        assert_eq!(all.len(), 52);
+        assert_eq!(all.faces().len(), 20);
+        assert!(all.faces().iter().all(|c| c.is_face()));
+        for suit in Suit::ALL {
+            assert_eq!(CardSet::suit(suit).len(), 13);
+            assert!(CardSet::suit(suit).iter().all(|c| c.is_suit(suit)));
+        }
+        let deck: CardSet = Card::deck(true).collect();
+        assert_eq!(deck, CardSet::ALL | CardSet::JOKER);
+        assert_eq!(deck.faces(), CardSet::FACES);
+        assert!(Suit::ALL
+            .iter()
+            .all(|s| !deck.of_suit(*s).contains(&Card::JOKER)));
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case("SA S2 HK S10", "S2 S10 SA HK")]
+    #[case("CA C2 DA D2", "D2 DA C2 CA")]
+    #[case("JK SA D2", "SA D2 JK")]
+    #[case("", "")]
+    fn test_iter(#[case] cards: &str, #[case] expected: &str) -> anyhow::Result<()> {
+        let set: CardSet = cards.parse()?;
+        assert_eq!(set.to_string(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_operations() -> anyhow::Result<()> {
+        let a: CardSet = "SA S2 HK".parse()?;
+        let b: CardSet = "S2 HK D5".parse()?;
+        assert_eq!((a | b).to_string(), "S2 SA HK D5");
+        assert_eq!((a & b).to_string(), "S2 HK");
+        assert_eq!((a - b).to_string(), "SA");
+        assert_eq!(a.of_suit(Suit::Spade).to_string(), "S2 SA");
+        assert_eq!(a.faces().to_string(), "SA HK");
+        assert_eq!(a.of_suit(Suit::Spade).last(), Some("SA".parse()?));
+        assert_eq!(CardSet::EMPTY.last(), None);
+
+        let mut c = a;
+        assert!(!c.insert("SA".parse()?));
+        assert!(c.remove(&"SA".parse()?));
+        assert!(!c.remove(&"SA".parse()?));
+        assert!(!c.contains(&"SA".parse()?));
+        assert_eq!(c.len(), 2);
+
+        assert_eq!(
+            "SA SA".parse::<CardSet>(),
+            Err(Error::DuplicatedCard("SA".parse()?))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_json() -> anyhow::Result<()> {
+        let set: CardSet = "SA S2 HK".parse()?;
+        let json = serde_json::to_string(&set)?;
+        assert_eq!(json, "[2,1,26]");
+        assert_eq!(serde_json::from_str::<CardSet>("[1,26,2]")?, set);
+        assert_eq!(serde_json::from_str::<CardSet>(r#"["SA","S2","HK"]"#)?, set);
+        // 同じカードが2度あれば読まない
+        assert!(serde_json::from_str::<CardSet>("[1,26,1]").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_card() {
+        let invalid = Card {
+            number: 0,
+            suit: Suit::Heart,
+        };
+        let mut set = CardSet::new();
+        assert!(!set.insert(invalid));
+        assert!(set.is_empty());
+        assert!(!set.contains(&invalid));
+        assert!(!set.remove(&invalid));
+        let invalid = Card {
+            number: 14,
+            suit: Suit::Club,
+        };
+        assert!(!set.insert(invalid));
+        assert!(set.is_empty());
+    }
+}
@@ -1,33 +1,96 @@
-use crate::card::Card;
+use crate::card::{Card, Hands};
+use crate::error::{Error, Result};
 use crate::player::{FieldPlayer, FieldPlayers, Players};
+use crate::rule::RuleSet;
 use rand::seq::SliceRandom;
 
-pub fn distribute_cards(players: &Players) -> (FieldPlayers, [Card; 2]) {
-    let mut v: Vec<u8> = (1..53).collect();
-    v.shuffle(&mut rand::thread_rng());
-    let players: FieldPlayers = players
-        .0
-        .iter()
-        .enumerate()
-        .map(|(pid, p)| {
-            FieldPlayer::new(
-                p.clone(),
-                (0..10)
-                    .map(|i| Card::try_from(v[(pid * 10) + i]).unwrap())
-                    .collect::<Vec<Card>>()
-                    .try_into()
-                    .unwrap(),
-            )
-        })
-        .collect::<Vec<FieldPlayer>>()
-        .try_into()
-        .unwrap();
-    let opens = [
-        Card::try_from(v[50]).unwrap(),
-        Card::try_from(v[51]).unwrap(),
-    ];
-
-    (players, opens)
+pub fn distribute_cards(players: &Players, rules: &RuleSet) -> Result<(FieldPlayers, Vec<Card>)> {
+    distribute_cards_with(players, rules, &mut rand::thread_rng())
+}
+
+/// `rules`に従って`rng`でシャッフルして配る。同じ状態の`rng`からは同じ手札とオープンになる。
+/// `rules`が正しくないか、`players`が`RuleSet::players`人でなければ`Error::InvalidRule`を返す。
+pub fn distribute_cards_with<R: rand::Rng + ?Sized>(
+    players: &Players,
+    rules: &RuleSet,
+    rng: &mut R,
+) -> Result<(FieldPlayers, Vec<Card>)> {
+    rules.validate_for(players.0.len())?;
+    let deal = Deal::shuffled_with_rules(rng, rules);
+    Ok((deal.field_players(players), deal.opens))
+}
+
+/// 席順の手札とオープン。5人ならオープンは2枚、ジョーカーを使えば3枚。
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Deal {
+    pub hands: Vec<Hands>,
+    pub opens: Vec<Card>,
+}
+
+impl Deal {
+    /// 人数で決まる枚数ずつ配られていて、52枚(ジョーカーがあれば53枚)が重複なく揃っていることを検証する。
+    pub fn new(hands: Vec<Hands>, opens: Vec<Card>) -> Result<Self> {
+        let rules = RuleSet::for_players(hands.len());
+        rules.validate()?;
+        if let Some(h) = hands.iter().find(|h| h.len() != rules.hand_size()) {
+            return Err(Error::InvalidHandsSize {
+                expected: rules.hand_size(),
+                actual: h.len(),
+            });
+        }
+        let mut seen = std::collections::HashSet::new();
+        if let Some(c) = hands
+            .iter()
+            .flatten()
+            .chain(opens.iter())
+            .find(|c| !seen.insert(**c))
+        {
+            return Err(Error::DuplicatedCard(*c));
+        }
+        let rules = RuleSet {
+            joker: hands
+                .iter()
+                .flatten()
+                .chain(opens.iter())
+                .any(Card::is_joker),
+            ..rules
+        };
+        if opens.len() != rules.opens() {
+            return Err(Error::InvalidHandsSize {
+                expected: rules.opens(),
+                actual: opens.len(),
+            });
+        }
+        Ok(Deal { hands, opens })
+    }
+
+    pub fn shuffled<R: rand::Rng + ?Sized>(rng: &mut R) -> Self {
+        Self::shuffled_with_rules(rng, &RuleSet::default())
+    }
+
+    /// `rules`の人数に、`rules`で使うカードを配る。
+    pub fn shuffled_with_rules<R: rand::Rng + ?Sized>(rng: &mut R, rules: &RuleSet) -> Self {
+        let mut v: Vec<Card> = Card::deck(rules.joker).collect();
+        v.shuffle(rng);
+        let mut cards = v.into_iter();
+        let hands: Vec<Hands> = (0..rules.players)
+            .map(|_| cards.by_ref().take(rules.hand_size()).collect())
+            .collect();
+        Deal {
+            hands,
+            opens: cards.collect(),
+        }
+    }
+
+    pub fn field_players(&self, players: &Players) -> FieldPlayers {
+        players
+            .0
+            .iter()
+            .zip(self.hands.iter())
+            .map(|(p, h)| FieldPlayer::new(p.clone(), h.iter().collect()))
+            .collect::<Vec<FieldPlayer>>()
+            .into()
+    }
 }
 
 #[cfg(test)]
@@ -35,21 +98,143 @@ mod tests {
     use super::*;
     use std::collections::HashSet;
 
+    #[rstest::rstest]
     #[test]
-    fn test_distribute() {
-        let players = Players::default();
-        let (field_players, opens) = distribute_cards(&players);
+    #[case(4)]
+    #[case(5)]
+    #[case(6)]
+    fn test_distribute(#[case] n: usize) -> anyhow::Result<()> {
+        let players = Players::with_len(n);
+        let rules = RuleSet::for_players(n);
+        let (field_players, opens) = distribute_cards(&players, &rules)?;
+        assert_eq!(field_players.0.len(), n);
+        assert!(field_players
+            .0
+            .iter()
+            .all(|p| p.hands.len() == rules.hand_size()));
+        assert_eq!(opens.len(), rules.opens());
 
         let mut s = HashSet::new();
-        field_players.0.map(|p| {
-            p.hands.map(|c| {
+        for p in field_players.0 {
+            for c in p.hands {
                 assert!(!s.contains(&c));
                 s.insert(c);
-            })
-        });
-        opens.map(|c| {
+            }
+        }
+        for c in opens {
             assert!(!s.contains(&c));
             s.insert(c);
-        });
+        }
+
+        // 人数がルールと合わない
+        assert!(matches!(
+            distribute_cards(&Players::with_len(n + 1), &rules),
+            Err(Error::InvalidRule(_))
+        ));
+        Ok(())
+    }
+
+    fn ids_deal() -> (Vec<Hands>, Vec<Card>) {
+        let hands: Vec<Hands> = (0..5)
+            .map(|i| {
+                (1..=10)
+                    .map(|j| Card::try_from((i * 10 + j) as u8).unwrap())
+                    .collect()
+            })
+            .collect();
+        (
+            hands,
+            vec![Card::try_from(51).unwrap(), Card::try_from(52).unwrap()],
+        )
+    }
+
+    #[test]
+    fn test_deal_new() -> anyhow::Result<()> {
+        let (hands, opens) = ids_deal();
+        let deal = Deal::new(hands.clone(), opens.clone())?;
+        let field_players = deal.field_players(&Players::default());
+        assert_eq!(field_players.0[2].hands, hands[2].iter().collect());
+
+        // 重複
+        let mut h = hands.clone();
+        h[0][0] = Card::try_from(52)?;
+        assert_eq!(
+            Deal::new(h, opens.clone()),
+            Err(Error::DuplicatedCard(Card::try_from(52)?))
+        );
+
+        // 枚数が足りない
+        let mut h = hands.clone();
+        h[4].pop();
+        assert_eq!(
+            Deal::new(h, opens.clone()),
+            Err(Error::InvalidHandsSize {
+                expected: 10,
+                actual: 9
+            })
+        );
+
+        // ジョーカーがあればオープンは3枚
+        let mut with_joker = opens.clone();
+        with_joker.push(Card::JOKER);
+        Deal::new(hands.clone(), with_joker)?;
+        let mut h = hands.clone();
+        h[0][0] = Card::JOKER;
+        assert_eq!(
+            Deal::new(h, opens.clone()),
+            Err(Error::InvalidHandsSize {
+                expected: 3,
+                actual: 2
+            })
+        );
+
+        // 人数が合わない
+        assert!(matches!(
+            Deal::new(hands[..3].to_vec(), opens),
+            Err(Error::InvalidRule(_))
+        ));
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(5, false, 10, 2)]
+    #[case(5, true, 10, 3)]
+    #[case(4, false, 12, 4)]
+    #[case(6, true, 8, 5)]
+    fn test_deal_shuffled(
+        #[case] players: usize,
+        #[case] joker: bool,
+        #[case] hand_size: usize,
+        #[case] opens: usize,
+    ) -> anyhow::Result<()> {
+        let rules = RuleSet {
+            joker,
+            ..RuleSet::for_players(players)
+        };
+        let deal = Deal::shuffled_with_rules(&mut rand::thread_rng(), &rules);
+        assert_eq!(deal.hands.len(), players);
+        assert!(deal.hands.iter().all(|h| h.len() == hand_size));
+        assert_eq!(deal.opens.len(), opens);
+        Deal::new(deal.hands, deal.opens)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_distribute_with() {
+        use rand::SeedableRng;
+
+        let players = Players::default();
+        let rules = RuleSet::default();
+        let deal = |seed| {
+            distribute_cards_with(
+                &players,
+                &rules,
+                &mut rand_chacha::ChaCha8Rng::seed_from_u64(seed),
+            )
+            .unwrap()
+        };
+        assert_eq!(deal(1), deal(1));
+        assert_ne!(deal(1), deal(2));
     }
 }
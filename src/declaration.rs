@@ -1,7 +1,9 @@
 use crate::card::{Card, Suit};
+use crate::error::{Error, Result};
 use crate::player::Player;
+use crate::rule::RuleSet;
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct Declaration {
     pub napoleon: Player,
     pub suit: Option<Suit>,
@@ -10,14 +12,22 @@ pub struct Declaration {
 }
 
 impl Declaration {
-    #[allow(dead_code)]
-    pub fn new(
+    /// 5人で遊ぶときの宣言。立ちは13から20。
+    pub fn new(napoleon: Player, suit: Option<Suit>, number: usize, aide: Card) -> Result<Self> {
+        Self::with_rules(napoleon, suit, number, aide, &RuleSet::default())
+    }
+
+    /// `rules`に従う宣言。立ちは`RuleSet::bid_numbers`のどれか。
+    pub fn with_rules(
         napoleon: Player,
         suit: Option<Suit>,
         number: usize,
         aide: Card,
-    ) -> anyhow::Result<Self> {
-        anyhow::ensure!(number > 12 && number < 21, "invalid declaration number");
+        rules: &RuleSet,
+    ) -> Result<Self> {
+        if !rules.bid_numbers().contains(&number) {
+            return Err(Error::InvalidDeclaration(number));
+        }
         Ok(Declaration {
             napoleon,
             suit,
@@ -26,9 +36,9 @@ impl Declaration {
         })
     }
 
-    #[allow(dead_code)]
-    fn base_score(&self) -> usize {
-        self.number - 12
+    /// 立ちちょうどで勝ったときの1単位の点数。人数で決まる立ちの下限で1になる。
+    pub fn base_score(&self, rules: &RuleSet) -> usize {
+        (self.number + 1).saturating_sub(rules.lowest_bid())
     }
 }
 
@@ -49,24 +59,48 @@ mod tests {
         Ok(())
     }
 
+    #[rstest::rstest]
     #[test]
-    fn declaration_new_invalid_number() -> anyhow::Result<()> {
+    #[case(12)]
+    #[case(11)]
+    fn declaration_new_invalid_number(#[case] number: usize) -> anyhow::Result<()> {
         assert!(Declaration::new(
             Player {
                 id: "a".to_string(),
             },
             None,
-            12,
+            number,
             Card::try_from(1)?,
         )
         .is_err());
         Ok(())
     }
 
+    #[rstest::rstest]
+    #[test]
+    #[case(4, 14, true)]
+    #[case(4, 13, false)]
+    #[case(6, 12, true)]
+    #[case(6, 11, false)]
+    #[case(6, 21, false)]
+    fn declaration_with_rules(
+        #[case] players: usize,
+        #[case] number: usize,
+        #[case] ok: bool,
+    ) -> anyhow::Result<()> {
+        let rules = RuleSet::for_players(players);
+        let d =
+            Declaration::with_rules(Player::default(), None, number, Card::try_from(1)?, &rules);
+        assert_eq!(d.is_ok(), ok);
+        Ok(())
+    }
+
     #[test]
     fn declaration_base_score() -> anyhow::Result<()> {
         let d = Declaration::new(Player::default(), None, 13, Card::try_from(1)?)?;
-        assert_eq!(d.base_score(), 1);
+        assert_eq!(d.base_score(&RuleSet::default()), 1);
+        assert_eq!(d.base_score(&RuleSet::for_players(4)), 0);
+        assert_eq!(d.base_score(&RuleSet::for_players(6)), 2);
         Ok(())
     }
 }
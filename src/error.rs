@@ -0,0 +1,91 @@
+use crate::bidding::Bid;
+use crate::card::{Card, Suit};
+use crate::player::Player;
+use crate::round::{Phase, UndoPolicy};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    InvalidCardId(u8),
+    InvalidNotation(String),
+    InvalidDeclaration(usize),
+    InvalidHandsSize { expected: usize, actual: usize },
+    DuplicatedCard(Card),
+    UnknownPlayer(Player),
+    PhaseViolation { action: &'static str, phase: Phase },
+    OutOfTurn { expected: Player, actual: Player },
+    NotInHands(Card),
+    IllegalPlay { card: Card, lead: Suit },
+    AlreadyPlayed(Player),
+    TrickFinished,
+    TrickNotFinished,
+    NotNapoleon(Player),
+    BidTooLow { highest: Bid },
+    DeclarationMismatch,
+    BiddingFinished,
+    NapoleonNotDecided,
+    DeclarationNotSet,
+    NotFinished,
+    NothingToSettle,
+    NotZeroSum,
+    UnsupportedVersion(u32),
+    Format(String),
+    InvalidRecord(String),
+    InvalidEvent(String),
+    InvalidRule(String),
+    UndoNotAllowed(UndoPolicy),
+    NothingToUndo,
+    NothingToRedo,
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::InvalidCardId(id) => write!(f, "invalid id \"{}\"", id),
+            Error::InvalidNotation(s) => write!(f, "invalid notation \"{}\"", s),
+            Error::InvalidDeclaration(n) => write!(f, "invalid declaration number {}", n),
+            Error::InvalidHandsSize { expected, actual } => {
+                write!(f, "must be {} cards, not {}", expected, actual)
+            }
+            Error::DuplicatedCard(c) => write!(f, "{} is used twice", c),
+            Error::UnknownPlayer(p) => write!(f, "\"{}\" is not found", p.id),
+            Error::PhaseViolation { action, phase } => {
+                write!(f, "cannot {} in {:?} phase", action, phase)
+            }
+            Error::OutOfTurn { expected, actual } => write!(
+                f,
+                "it is \"{}\"'s turn, not \"{}\"'s",
+                expected.id, actual.id
+            ),
+            Error::NotInHands(c) => write!(f, "{} is not in hands", c),
+            Error::IllegalPlay { card, lead } => {
+                write!(f, "{} cannot be played, must follow {}", card, lead)
+            }
+            Error::AlreadyPlayed(p) => write!(f, "\"{}\" has already played", p.id),
+            Error::TrickFinished => write!(f, "This Trick is already finished"),
+            Error::TrickNotFinished => write!(f, "This Trick is not finished yet"),
+            Error::NotNapoleon(p) => write!(f, "\"{}\" is not Napoleon", p.id),
+            Error::BidTooLow { highest } => write!(f, "bid must be higher than {:?}", highest),
+            Error::DeclarationMismatch => {
+                write!(f, "declaration does not match the result of bidding")
+            }
+            Error::BiddingFinished => write!(f, "bidding is already finished"),
+            Error::NapoleonNotDecided => write!(f, "Napoleon is not decided yet"),
+            Error::DeclarationNotSet => write!(f, "declaration is not set"),
+            Error::NotFinished => write!(f, "round is not finished"),
+            Error::NothingToSettle => write!(f, "there is no round to settle"),
+            Error::NotZeroSum => write!(f, "scores are not zero-sum"),
+            Error::UnsupportedVersion(v) => write!(f, "unsupported save version {}", v),
+            Error::Format(e) => write!(f, "invalid save data: {}", e),
+            Error::InvalidRecord(e) => write!(f, "invalid record: {}", e),
+            Error::InvalidEvent(e) => write!(f, "invalid event: {}", e),
+            Error::InvalidRule(e) => write!(f, "invalid rule: {}", e),
+            Error::UndoNotAllowed(p) => write!(f, "cannot undo under {:?} policy", p),
+            Error::NothingToUndo => write!(f, "there is nothing to undo"),
+            Error::NothingToRedo => write!(f, "there is nothing to redo"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,78 @@
+use crate::bidding::Bid;
+use crate::card::Card;
+use crate::cards::Deal;
+use crate::declaration::Declaration;
+use crate::player::{Player, Players};
+use crate::rule::RuleSet;
+use crate::settlement::Settlement;
+use crate::trick::Play;
+use crate::trick_result::TrickResult;
+
+/// `Round`の状態の変化。`Round::events`に起きた順に残り、`Round::replay`で`Round`を組み立て直せる。
+///
+/// プレイヤーの操作(`Bid`, `Passed`, `Declared`, `Exchanged`, `Played`)と、
+/// 操作から導かれる変化(`Dealt`, `TrickWon`, `Settled`)がある。
+/// `Dealt`は配り直しなら操作から導かれるが、ラウンドの始まりでは最初のイベントになる。
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum GameEvent {
+    Dealt {
+        players: Players,
+        seed: Option<u64>,
+        rules: RuleSet,
+        deal: Deal,
+    },
+    Bid {
+        player: Player,
+        bid: Bid,
+    },
+    Passed {
+        player: Player,
+    },
+    Declared(Declaration),
+    Exchanged {
+        player: Player,
+        discards: Vec<Card>,
+    },
+    Played(Play),
+    TrickWon(TrickResult),
+    Settled(Settlement),
+}
+
+impl GameEvent {
+    /// プレイヤーの操作かどうか。
+    pub fn is_action(&self) -> bool {
+        !matches!(
+            self,
+            GameEvent::Dealt { .. } | GameEvent::TrickWon(_) | GameEvent::Settled(_)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json() -> anyhow::Result<()> {
+        let player = Player::default();
+        let event = GameEvent::Bid {
+            player: player.clone(),
+            bid: "13S".parse()?,
+        };
+        let json = serde_json::to_value(&event)?;
+        assert_eq!(
+            json,
+            serde_json::json!({ "Bid": { "player": { "id": "a" }, "bid": { "number": 13, "suit": "Spade" } } })
+        );
+        assert_eq!(serde_json::from_value::<GameEvent>(json)?, event);
+
+        let event = GameEvent::Played(Play::new(player, Card::try_from(1)?));
+        let json = serde_json::to_value(&event)?;
+        assert_eq!(
+            json,
+            serde_json::json!({ "Played": { "player": { "id": "a" }, "card": 1 } })
+        );
+        assert_eq!(serde_json::from_value::<GameEvent>(json)?, event);
+        Ok(())
+    }
+}
@@ -1,61 +1,208 @@
+use crate::error::{Error, Result};
 use crate::player::{Player, Players};
 use crate::round::Round;
+use crate::rule::RuleSet;
+use crate::settlement::Settlement;
 
-#[allow(dead_code)]
-#[derive(serde::Serialize, serde::Deserialize)]
-struct PlayerScore {
-    player: Player,
-    score: usize,
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PlayerScore {
+    pub player: Player,
+    pub score: i32,
 }
 
-type PlayerScores = [PlayerScore; 5];
+type PlayerScores = Vec<PlayerScore>;
 
 impl PlayerScore {
     fn new(player: Player) -> Self {
         PlayerScore { player, score: 0 }
     }
 
-    #[allow(dead_code)]
-    fn add(&mut self, player: Player, score: usize) {
-        if self.player == player {
+    fn add(&mut self, player: &Player, score: i32) {
+        if self.player == *player {
             self.score += score;
         }
     }
 }
 
-#[allow(dead_code)]
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct Game {
     player_scores: PlayerScores,
+    rules: RuleSet,
     rounds: Vec<Round>,
+    settlements: Vec<Settlement>,
 }
 
 impl Game {
-    #[allow(dead_code)]
-    pub fn new(players: Players) -> Self {
+    pub fn new(players: Players) -> Result<Self> {
+        Self::with_rules(players, RuleSet::default())
+    }
+
+    /// 全てのラウンドを`rules`に従って遊ぶ。
+    /// `rules`が正しくないか、`players`が`RuleSet::players`人でなければ`Error::InvalidRule`を返す。
+    pub fn with_rules(players: Players, rules: RuleSet) -> Result<Self> {
+        rules.validate_for(players.0.len())?;
         let rounds: Vec<Round> = Vec::new();
-        Game {
-            player_scores: players.0.map(PlayerScore::new),
+        Ok(Game {
+            player_scores: players.0.into_iter().map(PlayerScore::new).collect(),
+            rules,
             rounds,
-        }
+            settlements: Vec::new(),
+        })
+    }
+
+    pub fn new_round(&mut self) -> Result<&mut Round> {
+        self.new_round_with_seed(rand::random())
     }
 
-    pub fn new_round(&mut self) -> &mut Round {
+    pub fn new_round_with_seed(&mut self, seed: u64) -> Result<&mut Round> {
         let players: Players = self
             .player_scores
             .iter()
             .map(|ps| ps.player.clone())
             .collect();
-        let round = Round::new(players);
+        let round = Round::new_with_rules(players, seed, self.rules)?;
         self.rounds.push(round);
-        self.rounds.last_mut().unwrap()
+        Ok(self.rounds.last_mut().unwrap())
+    }
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    pub fn rounds(&self) -> &[Round] {
+        &self.rounds
     }
 
-    // #[allow(dead_code)]
-    // fn get_scores(&self) {
-    //     let scores = self.players.iter().map(|u| PlayerScore::new(u.clone()));
-    //     self.round_results
-    //         .iter()
-    //         .map(|r| scores.map(|s| r.get_score()));
-    // }
+    pub fn player_scores(&self) -> &[PlayerScore] {
+        &self.player_scores
+    }
+
+    pub fn settlements(&self) -> &[Settlement] {
+        &self.settlements
+    }
+
+    /// 精算していない最初のラウンドを精算して、各プレイヤーの得点に加える。
+    /// 精算の全員が得点に加えられて合計が0のままでなければ、得点を変えずに`Error::NotZeroSum`を返す。
+    pub fn settle(&mut self) -> Result<&Settlement> {
+        let round = self
+            .rounds
+            .get(self.settlements.len())
+            .ok_or(Error::NothingToSettle)?;
+        let settlement = Settlement::new(round)?;
+        let mut player_scores = self.player_scores.clone();
+        for ps in player_scores.iter_mut() {
+            for (player, delta) in settlement.deltas.iter() {
+                ps.add(player, *delta);
+            }
+        }
+        let applied = settlement
+            .deltas
+            .iter()
+            .all(|(player, _)| player_scores.iter().any(|ps| ps.player == *player));
+        if !applied || player_scores.iter().map(|ps| ps.score).sum::<i32>() != 0 {
+            return Err(Error::NotZeroSum);
+        }
+        self.player_scores = player_scores;
+        self.settlements.push(settlement);
+        Ok(self.settlements.last().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::declaration::Declaration;
+    use crate::trick::Play;
+
+    fn play_round(game: &mut Game, napoleon: usize) -> anyhow::Result<()> {
+        let round = game.new_round()?;
+        let player = round.field_players.0[napoleon].player.clone();
+        let aide = round.field_players.0[(napoleon + 1) % 5].hands.to_vec()[0];
+        round.set_declaration(Declaration::new(player.clone(), None, 13, aide)?)?;
+        round.exchange(&player, round.opens.clone())?;
+        while let Some(p) = round.next_player() {
+            let card = round.legal_plays()[0];
+            round.play(Play::new(p, card))?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_settle() -> anyhow::Result<()> {
+        let players = Players::default();
+        let mut game = Game::new(players.clone())?;
+        assert_eq!(game.settle().err(), Some(Error::NothingToSettle));
+
+        play_round(&mut game, 0)?;
+        let deltas = game.settle()?.deltas.clone();
+        assert_eq!(game.settle().err(), Some(Error::NothingToSettle));
+        for (ps, (player, delta)) in game.player_scores().iter().zip(deltas.iter()) {
+            assert_eq!(ps.player, *player);
+            assert_eq!(ps.score, *delta);
+        }
+
+        play_round(&mut game, 2)?;
+        game.settle()?;
+        assert_eq!(game.settlements().len(), 2);
+        assert_eq!(
+            game.player_scores().iter().map(|ps| ps.score).sum::<i32>(),
+            0
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_settle_not_applied() -> anyhow::Result<()> {
+        let mut game = Game::new(Players::default())?;
+        play_round(&mut game, 0)?;
+        // 精算に出てくるプレイヤーが得点にいなければ、得点を変えない
+        game.player_scores[0].player = Player {
+            id: "z".to_string(),
+        };
+        let before = game.player_scores().to_vec();
+        assert_eq!(game.settle().err(), Some(Error::NotZeroSum));
+        assert_eq!(game.player_scores(), before);
+        assert!(game.settlements().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_json() -> anyhow::Result<()> {
+        let mut game = Game::new(Players::default())?;
+        play_round(&mut game, 0)?;
+        game.settle()?;
+        game.new_round()?;
+
+        let json = serde_json::to_string(&game)?;
+        let restored: Game = serde_json::from_str(&json)?;
+        assert_eq!(serde_json::to_string(&restored)?, json);
+        assert_eq!(restored.player_scores(), game.player_scores());
+        assert_eq!(restored.settlements(), game.settlements());
+        Ok(())
+    }
+
+    #[test]
+    fn test_settle_not_finished() -> anyhow::Result<()> {
+        let mut game = Game::new(Players::default())?;
+        game.new_round()?;
+        assert_eq!(game.settle().err(), Some(Error::NotFinished));
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_rules_invalid() {
+        // 人数がルールと合わない
+        assert!(matches!(
+            Game::new(Players::with_len(4)),
+            Err(Error::InvalidRule(_))
+        ));
+        let rules = RuleSet {
+            min_bid: 21,
+            ..Default::default()
+        };
+        assert!(matches!(
+            Game::with_rules(Players::default(), rules),
+            Err(Error::InvalidRule(_))
+        ));
+    }
 }
@@ -1,8 +1,22 @@
+pub mod agent;
+pub mod bidding;
 pub mod card;
+pub mod card_set;
 pub mod cards;
 pub mod declaration;
+pub mod error;
+pub mod event;
 pub mod game;
 pub mod player;
+mod playout;
+pub mod record;
 pub mod round;
+pub mod rule;
+pub mod save;
+pub mod settlement;
+pub mod solver;
 pub mod trick;
 pub mod trick_result;
+pub mod view;
+
+pub use error::{Error, Result};
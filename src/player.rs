@@ -1,4 +1,6 @@
-use crate::card::{Card, Hands};
+use crate::card::Card;
+use crate::card_set::CardSet;
+use crate::error::{Error, Result};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct Player {
@@ -13,30 +15,67 @@ impl Default for Player {
     }
 }
 
+/// `Player`をキーにした`HashMap`を、idをキーにしたオブジェクトとしてidの順に(デ)シリアライズする。
+pub(crate) mod player_map {
+    use super::Player;
+    use std::collections::{BTreeMap, HashMap};
+
+    pub fn serialize<S, V>(map: &HashMap<Player, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+        V: serde::Serialize,
+    {
+        let sorted: BTreeMap<&str, &V> = map.iter().map(|(p, v)| (p.id.as_str(), v)).collect();
+        serde::Serialize::serialize(&sorted, serializer)
+    }
+
+    pub fn deserialize<'de, D, V>(deserializer: D) -> Result<HashMap<Player, V>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+        V: serde::Deserialize<'de>,
+    {
+        let map: BTreeMap<String, V> = serde::Deserialize::deserialize(deserializer)?;
+        Ok(map.into_iter().map(|(id, v)| (Player { id }, v)).collect())
+    }
+}
+
+/// 席順のプレイヤー。人数は`RuleSet::players`に合わせる。
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
-pub struct Players(pub [Player; 5]);
+pub struct Players(pub Vec<Player>);
 
 impl Default for Players {
     fn default() -> Self {
-        Self(["a", "b", "c", "d", "e"].map(|s| Player { id: s.to_string() }))
+        Self::with_len(5)
+    }
+}
+
+impl Players {
+    /// idが`a`, `b`, `c`, ...の`n`人。
+    pub fn with_len(n: usize) -> Self {
+        (b'a'..)
+            .take(n)
+            .map(|c| Player {
+                id: char::from(c).to_string(),
+            })
+            .collect()
     }
 }
 
 impl From<Players> for Vec<Player> {
     fn from(players: Players) -> Self {
-        players.0.to_vec()
+        players.0
     }
 }
 
 impl From<Vec<Player>> for Players {
     fn from(players: Vec<Player>) -> Self {
-        Players(players.try_into().unwrap())
+        Players(players)
     }
 }
 
 impl std::iter::FromIterator<Player> for Players {
     fn from_iter<T: IntoIterator<Item = Player>>(iter: T) -> Self {
-        Self(iter.into_iter().collect::<Vec<_>>().try_into().unwrap())
+        Self(iter.into_iter().collect())
     }
 }
 
@@ -50,12 +89,12 @@ pub enum Role {
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct FieldPlayer {
     pub player: Player,
-    pub hands: Hands,
+    pub hands: CardSet,
     pub role: Role,
 }
 
 impl FieldPlayer {
-    pub fn new(player: Player, hands: Hands) -> Self {
+    pub fn new(player: Player, hands: CardSet) -> Self {
         FieldPlayer {
             player,
             hands,
@@ -67,34 +106,50 @@ impl FieldPlayer {
         self.role = role;
     }
 
-    pub fn choice_opens(&mut self, opens: [Card; 2], discard: [Card; 2]) {
-        let mut cards = self.hands.to_vec();
-        cards.append(&mut opens.to_vec());
-        self.hands = cards
-            .into_iter()
-            .filter(|c| !discard.iter().any(|g| g == c))
-            .collect::<Vec<Card>>()
-            .try_into()
-            .unwrap();
+    /// オープンを手札に加え、`discard`を捨てる。手札の枚数は変わらない。
+    pub fn choice_opens(&mut self, opens: &[Card], discard: &[Card]) -> Result<()> {
+        let size = self.hands.len();
+        let mut hands = self.hands;
+        hands.extend(opens.iter().copied());
+        if let Some(c) = discard.iter().find(|c| !hands.contains(c)) {
+            return Err(Error::NotInHands(*c));
+        }
+        for c in discard.iter() {
+            if !hands.remove(c) {
+                return Err(Error::DuplicatedCard(*c));
+            }
+        }
+        if hands.len() != size {
+            return Err(Error::InvalidHandsSize {
+                expected: size,
+                actual: hands.len(),
+            });
+        }
+        self.hands = hands;
+        Ok(())
     }
 
     pub fn has(&self, card: &Card) -> bool {
-        self.hands.iter().any(|c| c == card)
+        self.hands.contains(card)
+    }
+
+    pub(crate) fn remove(&mut self, card: &Card) {
+        self.hands.remove(card);
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
-pub struct FieldPlayers(pub [FieldPlayer; 5]);
+pub struct FieldPlayers(pub Vec<FieldPlayer>);
 
 impl From<FieldPlayers> for Vec<FieldPlayer> {
     fn from(players: FieldPlayers) -> Self {
-        players.0.to_vec()
+        players.0
     }
 }
 
 impl From<Vec<FieldPlayer>> for FieldPlayers {
     fn from(players: Vec<FieldPlayer>) -> Self {
-        FieldPlayers(players.try_into().unwrap())
+        FieldPlayers(players)
     }
 }
 
@@ -0,0 +1,163 @@
+//! 全員の手札が分かっているときのプレイの段階。探索で何度も先を読むために`Round`より軽くしてある。
+use crate::card::{lead_suit, Card, Suit};
+use crate::card_set::CardSet;
+use crate::error::{Error, Result};
+use crate::player::Role;
+use crate::round::{Phase, Round, Team};
+use crate::rule::RuleSet;
+use crate::trick::legal_cards;
+use crate::trick_result::{judge, TrickCards};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PlayState {
+    /// 席順の手札
+    pub hands: Vec<CardSet>,
+    pub trump: Option<Suit>,
+    pub rules: RuleSet,
+    /// 立ちの枚数
+    pub number: usize,
+    /// `Declaration::base_score`
+    pub base_score: usize,
+    pub napoleon: usize,
+    pub aide: Option<usize>,
+    /// 今のトリックで最初に出した席
+    pub leader: usize,
+    /// 今のトリックに出したカード。`leader`から順に並ぶ。
+    pub trick: TrickCards,
+    pub trick_no: u8,
+    /// ナポレオン軍が取った絵札の枚数
+    pub napoleon_points: usize,
+}
+
+impl PlayState {
+    /// プレイ中の`round`から作る。
+    pub fn from_round(round: &Round) -> Result<Self> {
+        let Phase::Playing { trick_no } = round.phase() else {
+            return Err(Error::PhaseViolation {
+                action: "search",
+                phase: round.phase(),
+            });
+        };
+        let declaration = round.declaration().ok_or(Error::DeclarationNotSet)?;
+        let players = &round.field_players.0;
+        let seat_of = |role: Role| players.iter().position(|p| p.role == role);
+        let leader = match round.trick().plays.first() {
+            Some(play) => players.iter().position(|p| p.player == play.player),
+            None => round
+                .next_player()
+                .and_then(|next| players.iter().position(|p| p.player == next)),
+        }
+        .ok_or(Error::DeclarationNotSet)?;
+        let (napoleon_points, _) = round.team_score()?;
+        Ok(PlayState {
+            hands: players.iter().map(|p| p.hands).collect(),
+            trump: declaration.suit,
+            rules: *round.rules(),
+            number: declaration.number,
+            base_score: declaration.base_score(round.rules()),
+            napoleon: seat_of(Role::Napoleon).ok_or(Error::DeclarationNotSet)?,
+            aide: seat_of(Role::Aide),
+            leader,
+            trick: round.trick().plays.iter().map(|p| p.card).collect(),
+            trick_no,
+            napoleon_points,
+        })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        usize::from(self.trick_no) > self.rules.hand_size()
+    }
+
+    /// 次に出す席。
+    pub fn to_move(&self) -> usize {
+        (self.leader + self.trick.len()) % self.hands.len()
+    }
+
+    pub fn is_napoleon_side(&self, seat: usize) -> bool {
+        seat == self.napoleon || Some(seat) == self.aide
+    }
+
+    pub fn legal(&self) -> CardSet {
+        legal_cards(
+            self.hands[self.to_move()],
+            lead_suit(self.trick.iter()),
+            self.trump,
+            &self.rules,
+        )
+    }
+
+    /// 次の席が`card`を出す。全員が出したらトリックの勝者が次のトリックの最初に出す。
+    pub fn play(&mut self, card: Card) {
+        let seat = self.to_move();
+        self.hands[seat].remove(&card);
+        self.trick.push(card);
+        if self.trick.len() == self.hands.len() {
+            let (winner, faces) = judge(&self.trick, self.trump, self.trick_no, &self.rules);
+            let winner = (self.leader + winner) % self.hands.len();
+            if self.is_napoleon_side(winner) {
+                self.napoleon_points += faces.len();
+            }
+            self.leader = winner;
+            self.trick.clear();
+            self.trick_no += 1;
+        }
+    }
+
+    /// 最後まで出したときに勝った軍。
+    pub fn winner(&self) -> Team {
+        crate::settlement::outcome(self.number, self.base_score, self.napoleon_points).0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::declaration::Declaration;
+    use crate::player::Players;
+    use crate::settlement::Settlement;
+    use crate::trick::Play;
+
+    #[rstest::rstest]
+    #[test]
+    #[case(1)]
+    #[case(2)]
+    #[case(3)]
+    fn test_same_as_round(#[case] seed: u64) -> anyhow::Result<()> {
+        let players = Players::default();
+        let mut round = Round::new_with_seed(players.clone(), seed)?;
+        let aide = round.field_players.0[2].hands.to_vec()[3];
+        let declaration = Declaration::new(players.0[1].clone(), Some(Suit::Heart), 14, aide)?;
+        round.set_declaration(declaration)?;
+        round.exchange(&players.0[1], round.opens.clone())?;
+        for _ in 0..3 {
+            round.play(Play::new(
+                round.next_player().unwrap(),
+                round.legal_plays()[0],
+            ))?;
+        }
+
+        let mut state = PlayState::from_round(&round)?;
+        while !state.is_finished() {
+            let seat = state.to_move();
+            assert_eq!(Some(&players.0[seat]), round.next_player().as_ref());
+            assert_eq!(state.legal().to_vec(), round.legal_plays());
+            let card = round.legal_plays()[0];
+            state.play(card);
+            round.play(Play::new(players.0[seat].clone(), card))?;
+        }
+        let settlement = Settlement::new(&round)?;
+        assert_eq!(state.napoleon_points, settlement.napoleon_score);
+        assert_eq!(state.winner(), settlement.winner);
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_round_not_playing() -> anyhow::Result<()> {
+        let round = Round::new_with_seed(Players::default(), 1)?;
+        assert!(matches!(
+            PlayState::from_round(&round),
+            Err(Error::PhaseViolation { .. })
+        ));
+        Ok(())
+    }
+}
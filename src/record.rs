@@ -0,0 +1,733 @@
+//! ラウンドの棋譜。
+//!
+//! `[名前 "値"]`のタグに続けて、トリックごとに`1. a:SA b:S3 c:S4 d:S5 e:S6`のように書く。
+//!
+//! ```text
+//! [Players "a b c d e"]
+//! [Seed "42"]
+//! [Hand "a" "SA S3 ..."]
+//! [Opens "D2 C7"]
+//! [Bidding "a:13S b:pass c:14H d:pass e:pass a:pass"]
+//! [Declaration "c 14H HQ"]
+//! [Discard "D2 S3"]
+//! [Result "Napoleon 15"]
+//!
+//! 1. c:HA d:H3 e:H4 a:H5 b:H6
+//! ```
+//!
+//! `Hand`は席順に全員分書く。`Seed`がなければ`Hand`と`Opens`から始める。
+//! 全員がパスして配り直したときは、`Bidding`に続けて配り直した`Seed`、`Hand`、`Opens`と`Bidding`を書く。
+//! 既定と違うルールで遊んだときは`[Rules "same2=false"]`のように`RuleSet`を書く。
+//! 5人以外で遊んだときは`[Rules "players=4"]`のように人数を書き、`Players`もその人数にする。
+//! プレイヤーのidは空白、`:`、`"`を含んではならない。
+use crate::bidding::Bid;
+use crate::card::{format_hands, parse_hands, Card, Hands};
+use crate::cards::Deal;
+use crate::declaration::Declaration;
+use crate::error::{Error, Result};
+use crate::event::GameEvent;
+use crate::player::{Player, Players, Role};
+use crate::round::{Phase, Round, Team};
+use crate::rule::RuleSet;
+use crate::settlement::Settlement;
+use crate::trick::Play;
+
+type Bids = [(Player, Option<Bid>)];
+
+/// 全員がパスして配り直した回の配り方と競りの経過。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redeal {
+    pub seed: Option<u64>,
+    pub deal: Deal,
+    pub bids: Vec<(Player, Option<Bid>)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub players: Players,
+    pub seed: Option<u64>,
+    pub rules: RuleSet,
+    /// 配り直す前の回。配った順に並ぶ。
+    pub redeals: Vec<Redeal>,
+    /// 交換する前の配り方
+    pub deal: Deal,
+    /// 競りの経過。`None`はパス。
+    pub bids: Vec<(Player, Option<Bid>)>,
+    pub declaration: Option<Declaration>,
+    pub discards: Option<Vec<Card>>,
+    /// 出されたカード。途中のトリックも含む。
+    pub plays: Vec<Play>,
+    /// 勝った軍とナポレオン軍が取った絵札の枚数
+    pub result: Option<(Team, usize)>,
+}
+
+fn invalid(s: impl std::fmt::Display) -> Error {
+    Error::InvalidRecord(s.to_string())
+}
+
+/// 手札を番号の順に並べる。
+fn sorted(deal: &Deal) -> Deal {
+    let mut deal = deal.clone();
+    for hands in deal.hands.iter_mut() {
+        hands.sort_by_key(|c| u8::from(*c));
+    }
+    deal
+}
+
+impl Record {
+    /// 今の`round`までの棋譜を`GameEvent`の列から作る。配り直した回の配り方と競りも含む。
+    pub fn new(round: &Round) -> Self {
+        match round.events().first() {
+            Some(GameEvent::Dealt { .. }) => Self::from_events(round),
+            // 古い保存データから移行したラウンドは配ったときのイベントを持たない
+            _ => Self::from_state(round),
+        }
+    }
+
+    fn from_events(round: &Round) -> Self {
+        let mut dealings: Vec<Redeal> = Vec::new();
+        let mut declaration = None;
+        let mut discards = None;
+        let mut plays = Vec::new();
+        let mut result = None;
+        for event in round.events() {
+            match event {
+                GameEvent::Dealt { seed, deal, .. } => {
+                    // 誰も宣言しないうちに配り直した配り方は残らない
+                    if dealings.last().is_some_and(|d| d.bids.is_empty()) {
+                        dealings.pop();
+                    }
+                    dealings.push(Redeal {
+                        seed: *seed,
+                        deal: sorted(deal),
+                        bids: Vec::new(),
+                    });
+                }
+                GameEvent::Bid { player, bid } => {
+                    if let Some(d) = dealings.last_mut() {
+                        d.bids.push((player.clone(), Some(*bid)));
+                    }
+                }
+                GameEvent::Passed { player } => {
+                    if let Some(d) = dealings.last_mut() {
+                        d.bids.push((player.clone(), None));
+                    }
+                }
+                GameEvent::Declared(d) => declaration = Some(d.clone()),
+                GameEvent::Exchanged { discards: d, .. } => discards = Some(d.clone()),
+                GameEvent::Played(play) => plays.push(play.clone()),
+                GameEvent::TrickWon(_) => {}
+                GameEvent::Settled(s) => result = Some((s.winner, s.napoleon_score)),
+            }
+        }
+        let Some(last) = dealings.pop() else {
+            return Self::from_state(round);
+        };
+        Record {
+            players: players_of(round),
+            seed: last.seed,
+            rules: *round.rules(),
+            redeals: dealings,
+            deal: last.deal,
+            bids: last.bids,
+            declaration,
+            discards,
+            plays,
+            result,
+        }
+    }
+
+    /// 今の状態から作る。配り方は、今の手札に出したカードと捨て札を戻して作る。
+    fn from_state(round: &Round) -> Self {
+        let plays: Vec<Play> = round
+            .trick_results()
+            .iter()
+            .flat_map(|r| r.trick.iter())
+            .chain(round.trick().plays.iter())
+            .cloned()
+            .collect();
+        let hands: Vec<Hands> = round
+            .field_players
+            .0
+            .iter()
+            .map(|field_player| {
+                let mut hands = field_player.hands;
+                hands.extend(
+                    plays
+                        .iter()
+                        .filter(|p| p.player == field_player.player)
+                        .map(|p| p.card),
+                );
+                if let (Role::Napoleon, Some(discards)) = (&field_player.role, round.discards()) {
+                    hands.extend(discards.iter().copied());
+                    hands -= round.opens.iter().collect();
+                }
+                let mut hands = hands.to_vec();
+                hands.sort_by_key(|c| u8::from(*c));
+                hands
+            })
+            .collect();
+        Record {
+            players: players_of(round),
+            seed: round.seed(),
+            rules: *round.rules(),
+            redeals: Vec::new(),
+            deal: Deal {
+                hands,
+                opens: round.opens.clone(),
+            },
+            bids: round.bidding().bids().to_vec(),
+            declaration: round.declaration().cloned(),
+            discards: round.discards().map(<[Card]>::to_vec),
+            plays,
+            result: Settlement::new(round)
+                .ok()
+                .map(|s| (s.winner, s.napoleon_score)),
+        }
+    }
+
+    /// 配った順のシード、配り方と競りの経過。最後が今の回。
+    fn dealings(&self) -> impl Iterator<Item = (Option<u64>, &Deal, &Bids)> {
+        self.redeals
+            .iter()
+            .map(|r| (r.seed, &r.deal, r.bids.as_slice()))
+            .chain([(self.seed, &self.deal, self.bids.as_slice())])
+    }
+
+    /// `seed`があれば、そこから配った配り方が`deal`と並びを除いて一致することを確かめて返す。
+    fn dealt(&self, seed: Option<u64>, deal: &Deal) -> Result<Deal> {
+        use rand::SeedableRng;
+
+        let Some(seed) = seed else {
+            return Ok(deal.clone());
+        };
+        let dealt = Deal::shuffled_with_rules(
+            &mut rand_chacha::ChaCha8Rng::seed_from_u64(seed),
+            &self.rules,
+        );
+        let same = |a: &[Card], b: &[Card]| a.len() == b.len() && a.iter().all(|c| b.contains(c));
+        let hands = dealt.hands.len() == deal.hands.len()
+            && dealt
+                .hands
+                .iter()
+                .zip(deal.hands.iter())
+                .all(|(a, b)| same(a, b));
+        if !hands || !same(&dealt.opens, &deal.opens) {
+            return Err(invalid("hands do not match the seed"));
+        }
+        Ok(dealt)
+    }
+
+    /// 棋譜の通りにルールに従って進めた`Round`を返す。棋譜と食い違えばエラーになる。
+    pub fn replay(&self) -> Result<Round> {
+        let dealings: Vec<_> = self.dealings().collect();
+        let (seed, deal, _) = dealings[0];
+        let deal = self.dealt(seed, deal)?;
+        let mut round = match seed {
+            Some(seed) => Round::new_with_rules(self.players.clone(), seed, self.rules)?,
+            None => Round::from_deal_with_rules(self.players.clone(), deal, self.rules)?,
+        };
+        for (i, (seed, deal, bids)) in dealings.into_iter().enumerate() {
+            if i > 0 {
+                // 前の回の競りは全員のパスで終わって配り直している
+                if !round.bidding().bids().is_empty() {
+                    return Err(invalid("bidding does not end in a redeal"));
+                }
+                round.apply(GameEvent::Dealt {
+                    players: self.players.clone(),
+                    seed,
+                    rules: self.rules,
+                    deal: self.dealt(seed, deal)?,
+                })?;
+            }
+            for (player, bid) in bids.iter() {
+                match bid {
+                    Some(bid) => round.bid(player, *bid)?,
+                    None => round.pass(player)?,
+                }
+            }
+        }
+        if let Some(declaration) = &self.declaration {
+            round.set_declaration(declaration.clone())?;
+        }
+        if let Some(discards) = &self.discards {
+            let napoleon = self
+                .declaration
+                .as_ref()
+                .ok_or(Error::DeclarationNotSet)?
+                .napoleon
+                .clone();
+            round.exchange(&napoleon, discards.clone())?;
+        }
+        for play in self.plays.iter() {
+            round.play(play.clone())?;
+        }
+        if let Some(result) = self.result {
+            let settlement = Settlement::new(&round)?;
+            if (settlement.winner, settlement.napoleon_score) != result {
+                return Err(invalid("result does not match the plays"));
+            }
+        } else if round.phase() == Phase::Finished {
+            return Err(invalid("result is missing"));
+        }
+        Ok(round)
+    }
+}
+
+fn players_of(round: &Round) -> Players {
+    round
+        .field_players
+        .0
+        .iter()
+        .map(|p| p.player.clone())
+        .collect()
+}
+
+fn format_bid(bid: &Option<Bid>) -> String {
+    match bid {
+        Some(bid) => bid.to_string(),
+        None => "pass".to_string(),
+    }
+}
+
+fn format_team(team: Team) -> &'static str {
+    match team {
+        Team::Napoleon => "Napoleon",
+        Team::Union => "Union",
+    }
+}
+
+impl std::fmt::Display for Record {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let ids: Vec<&str> = self.players.0.iter().map(|p| p.id.as_str()).collect();
+        writeln!(f, "[Players \"{}\"]", ids.join(" "))?;
+        for (i, (seed, deal, bids)) in self.dealings().enumerate() {
+            if let Some(seed) = seed {
+                writeln!(f, "[Seed \"{}\"]", seed)?;
+            }
+            if i == 0 && self.rules != RuleSet::default() {
+                writeln!(f, "[Rules \"{}\"]", self.rules)?;
+            }
+            for (id, hands) in ids.iter().zip(deal.hands.iter()) {
+                writeln!(f, "[Hand \"{}\" \"{}\"]", id, format_hands(hands))?;
+            }
+            writeln!(f, "[Opens \"{}\"]", format_hands(&deal.opens))?;
+            if !bids.is_empty() {
+                let bids: Vec<String> = bids
+                    .iter()
+                    .map(|(p, b)| format!("{}:{}", p.id, format_bid(b)))
+                    .collect();
+                writeln!(f, "[Bidding \"{}\"]", bids.join(" "))?;
+            }
+        }
+        if let Some(d) = &self.declaration {
+            let bid = Bid {
+                number: d.number,
+                suit: d.suit,
+            };
+            writeln!(f, "[Declaration \"{} {} {}\"]", d.napoleon.id, bid, d.aide)?;
+        }
+        if let Some(discards) = &self.discards {
+            writeln!(f, "[Discard \"{}\"]", format_hands(discards))?;
+        }
+        if let Some((team, score)) = self.result {
+            writeln!(f, "[Result \"{} {}\"]", format_team(team), score)?;
+        }
+        if !self.plays.is_empty() {
+            writeln!(f)?;
+        }
+        for (i, trick) in self.plays.chunks(self.rules.players).enumerate() {
+            let plays: Vec<String> = trick
+                .iter()
+                .map(|p| format!("{}:{}", p.player.id, p.card))
+                .collect();
+            writeln!(f, "{}. {}", i + 1, plays.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// `[Name "v1" "v2"]`を名前と値に分ける。
+fn parse_tag(line: &str) -> Result<(&str, Vec<&str>)> {
+    let inner = line
+        .strip_prefix('[')
+        .and_then(|l| l.strip_suffix(']'))
+        .ok_or_else(|| invalid(line))?;
+    let (name, rest) = inner.split_once(' ').ok_or_else(|| invalid(line))?;
+    let values: Vec<&str> = rest.split('"').collect();
+    // 引用符の外側は空白だけでなければならない
+    if values.len().is_multiple_of(2) || values.iter().step_by(2).any(|v| !v.trim().is_empty()) {
+        return Err(invalid(line));
+    }
+    Ok((name, values.into_iter().skip(1).step_by(2).collect()))
+}
+
+fn single<'a>(name: &str, values: &[&'a str]) -> Result<&'a str> {
+    match values {
+        [v] => Ok(v),
+        _ => Err(invalid(format!("{} must have one value", name))),
+    }
+}
+
+fn parse_player(id: &str, players: &Option<Players>) -> Result<Player> {
+    players
+        .as_ref()
+        .ok_or_else(|| invalid("Players must come first"))?
+        .0
+        .iter()
+        .find(|p| p.id == id)
+        .cloned()
+        .ok_or_else(|| Error::UnknownPlayer(Player { id: id.to_string() }))
+}
+
+fn parse_pair<'a>(s: &'a str, players: &Option<Players>) -> Result<(Player, &'a str)> {
+    let (id, value) = s.rsplit_once(':').ok_or_else(|| invalid(s))?;
+    Ok((parse_player(id, players)?, value))
+}
+
+impl std::str::FromStr for Record {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut players: Option<Players> = None;
+        let mut seed = None;
+        let mut rules = RuleSet::default();
+        let mut hands: Vec<(Player, Hands)> = Vec::new();
+        let mut opens = None;
+        let mut bids = Vec::new();
+        let mut declaration = None;
+        let mut discards = None;
+        let mut plays = Vec::new();
+        let mut result = None;
+        let mut redeals = Vec::new();
+
+        for line in s.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            if !line.starts_with('[') {
+                let (no, trick) = line.split_once('.').ok_or_else(|| invalid(line))?;
+                if no.parse::<usize>() != Ok(plays.len() / rules.players + 1) {
+                    return Err(invalid(line));
+                }
+                for pair in trick.split_whitespace() {
+                    let (player, card) = parse_pair(pair, &players)?;
+                    plays.push(Play::new(player, card.parse()?));
+                }
+                continue;
+            }
+            let (name, values) = parse_tag(line)?;
+            if matches!(name, "Seed" | "Hand" | "Opens") && !bids.is_empty() {
+                // 競りの後に配り方が続けば、全員がパスして配り直している
+                redeals.push((
+                    seed.take(),
+                    std::mem::take(&mut hands),
+                    opens.take(),
+                    std::mem::take(&mut bids),
+                ));
+            }
+            match name {
+                "Players" => {
+                    let ids: Vec<Player> = single(name, &values)?
+                        .split_whitespace()
+                        .map(|id| Player { id: id.to_string() })
+                        .collect();
+                    players = Some(ids.into());
+                }
+                "Seed" => {
+                    let v = single(name, &values)?;
+                    seed = Some(v.parse().map_err(|_| invalid(line))?);
+                }
+                "Rules" => rules = single(name, &values)?.parse()?,
+                "Hand" => match values[..] {
+                    [id, cards] => hands.push((parse_player(id, &players)?, parse_hands(cards)?)),
+                    _ => return Err(invalid(line)),
+                },
+                "Opens" => opens = Some(parse_hands(single(name, &values)?)?),
+                "Bidding" => {
+                    for pair in single(name, &values)?.split_whitespace() {
+                        let (player, bid) = parse_pair(pair, &players)?;
+                        let bid = match bid {
+                            "pass" => None,
+                            bid => Some(bid.parse()?),
+                        };
+                        bids.push((player, bid));
+                    }
+                }
+                "Declaration" => {
+                    let v = single(name, &values)?;
+                    let [id, bid, aide] = v.split_whitespace().collect::<Vec<_>>()[..] else {
+                        return Err(invalid(line));
+                    };
+                    let bid: Bid = bid.parse()?;
+                    declaration = Some((parse_player(id, &players)?, bid, aide.parse()?));
+                }
+                "Discard" => discards = Some(parse_hands(single(name, &values)?)?),
+                "Result" => {
+                    let v = single(name, &values)?;
+                    let (team, score) = v.split_once(' ').ok_or_else(|| invalid(line))?;
+                    let team = match team {
+                        "Napoleon" => Team::Napoleon,
+                        "Union" => Team::Union,
+                        _ => return Err(invalid(line)),
+                    };
+                    result = Some((team, score.parse().map_err(|_| invalid(line))?));
+                }
+                _ => return Err(invalid(format!("unknown tag {}", name))),
+            }
+        }
+
+        let players = players.ok_or_else(|| invalid("Players is missing"))?;
+        if players.0.len() != rules.players {
+            return Err(invalid("Players does not match the rules"));
+        }
+        let deal = |hands: Vec<(Player, Hands)>, opens: Option<Vec<Card>>| -> Result<Deal> {
+            let hands: Vec<Hands> = players
+                .0
+                .iter()
+                .map(|p| {
+                    hands
+                        .iter()
+                        .find(|(q, _)| q == p)
+                        .map(|(_, h)| h.clone())
+                        .ok_or_else(|| invalid(format!("Hand of {} is missing", p.id)))
+                })
+                .collect::<Result<_>>()?;
+            let opens = opens.ok_or_else(|| invalid("Opens is missing"))?;
+            Deal::new(hands, opens)
+        };
+        let redeals = redeals
+            .into_iter()
+            .map(|(seed, hands, opens, bids)| {
+                Ok(Redeal {
+                    seed,
+                    deal: deal(hands, opens)?,
+                    bids,
+                })
+            })
+            .collect::<Result<_>>()?;
+        let deal = deal(hands, opens)?;
+        // 立ちの下限は人数で決まるので、ルールが揃ってから宣言を作る
+        let declaration = declaration
+            .map(|(player, bid, aide)| {
+                Declaration::with_rules(player, bid.suit, bid.number, aide, &rules)
+            })
+            .transpose()?;
+        Ok(Record {
+            players,
+            seed,
+            rules,
+            redeals,
+            deal,
+            bids,
+            declaration,
+            discards,
+            plays,
+            result,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule::Preset;
+
+    fn play_round(seed: u64, n: usize, rules: RuleSet) -> anyhow::Result<Round> {
+        let players = Players::default();
+        let mut round = Round::new_with_rules(players.clone(), seed, rules)?;
+        round.pass(&players.0[0])?;
+        round.bid(&players.0[1], "13S".parse()?)?;
+        round.bid(&players.0[2], "14H".parse()?)?;
+        for i in [3, 4, 1] {
+            round.pass(&players.0[i])?;
+        }
+        let aide = round.field_players.0[3].hands.to_vec()[0];
+        round.declare(aide)?;
+        let napoleon = &round.field_players.0[2];
+        let mut discards: Vec<Card> = napoleon.hands.iter().take(rules.opens() - 1).collect();
+        discards.push(round.opens[1]);
+        round.exchange(&players.0[2], discards)?;
+        for _ in 0..n {
+            let Some(player) = round.next_player() else {
+                break;
+            };
+            let card = round.legal_plays()[0];
+            round.play(Play::new(player, card))?;
+        }
+        Ok(round)
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(1, 0, Preset::Standard, false)]
+    #[case(2, 23, Preset::Standard, false)]
+    #[case(3, 50, Preset::Standard, false)]
+    #[case(4, 50, Preset::Classic, false)]
+    #[case(5, 50, Preset::Standard, true)]
+    fn test_round_trip(
+        #[case] seed: u64,
+        #[case] n: usize,
+        #[case] preset: Preset,
+        #[case] joker: bool,
+    ) -> anyhow::Result<()> {
+        let rules = RuleSet {
+            joker,
+            ..preset.into()
+        };
+        let round = play_round(seed, n, rules)?;
+        let record = Record::new(&round);
+        let text = record.to_string();
+        let parsed: Record = text.parse()?;
+        assert_eq!(parsed, record);
+        assert_eq!(parsed.to_string(), text);
+
+        let replayed = record.replay()?;
+        assert_eq!(
+            serde_json::to_string(&replayed)?,
+            serde_json::to_string(&round)?
+        );
+
+        // シードがなくても配り方から再現できる
+        let record = Record {
+            seed: None,
+            ..record
+        };
+        let replayed = record.replay()?;
+        assert_eq!(Record::new(&replayed), record);
+        assert_eq!(replayed.trick_results(), round.trick_results());
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(4)]
+    #[case(6)]
+    fn test_round_trip_players(#[case] n: usize) -> anyhow::Result<()> {
+        let rules = RuleSet::for_players(n);
+        let players = Players::with_len(n);
+        let mut round = Round::new_with_rules(players.clone(), 1, rules)?;
+        let aide = round.field_players.0[1].hands.to_vec()[0];
+        let d = Declaration::with_rules(players.0[0].clone(), None, rules.min_bid, aide, &rules)?;
+        round.set_declaration(d)?;
+        round.exchange(&players.0[0], round.opens.clone())?;
+        while let Some(player) = round.next_player() {
+            let card = round.legal_plays()[0];
+            round.play(Play::new(player, card))?;
+        }
+        let record = Record::new(&round);
+        let text = record.to_string();
+        assert!(text.contains(&format!("players={}", n)));
+        assert!(text.contains(&format!("\n{}. ", rules.hand_size())));
+        let parsed: Record = text.parse()?;
+        assert_eq!(parsed, record);
+        assert_eq!(parsed.replay()?.trick_results(), round.trick_results());
+
+        // Playersの人数がルールと合わない
+        let text = text.replace(&format!("[Rules \"{}\"]", rules), "");
+        assert!(matches!(
+            text.parse::<Record>(),
+            Err(Error::InvalidRecord(_))
+        ));
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(Some(1))]
+    #[case(None)]
+    fn test_round_trip_redeal(#[case] seed: Option<u64>) -> anyhow::Result<()> {
+        use rand::SeedableRng;
+
+        let players = Players::default();
+        let mut round = match seed {
+            Some(seed) => Round::new_with_seed(players.clone(), seed)?,
+            None => Round::from_deal(
+                players.clone(),
+                Deal::shuffled(&mut rand_chacha::ChaCha8Rng::seed_from_u64(1)),
+            )?,
+        };
+        for p in players.0.iter() {
+            round.pass(p)?;
+        }
+        round.pass(&players.0[0])?;
+        round.bid(&players.0[1], "13S".parse()?)?;
+        for i in [2, 3, 4] {
+            round.pass(&players.0[i])?;
+        }
+        let aide = round.field_players.0[2].hands.to_vec()[0];
+        round.declare(aide)?;
+        round.exchange(&players.0[1], round.opens.clone())?;
+        for _ in 0..7 {
+            let player = round.next_player().unwrap();
+            round.play(Play::new(player, round.legal_plays()[0]))?;
+        }
+
+        let record = Record::new(&round);
+        assert_eq!(record.redeals.len(), 1);
+        assert_eq!(record.redeals[0].seed, seed);
+        assert_eq!(record.redeals[0].bids.len(), 5);
+        assert_eq!(record.bids.len(), 5);
+        let text = record.to_string();
+        assert_eq!(text.matches("[Bidding ").count(), 2);
+        let parsed: Record = text.parse()?;
+        assert_eq!(parsed, record);
+        let replayed = parsed.replay()?;
+        assert_eq!(Record::new(&replayed), record);
+        assert_eq!(replayed.trick_results(), round.trick_results());
+        assert_eq!(replayed.trick().plays, round.trick().plays);
+
+        // 配り直す前の競りが全員のパスで終わっていない
+        let mut r = record.clone();
+        r.redeals[0].bids[4] = (players.0[4].clone(), Some("13S".parse()?));
+        assert_eq!(
+            r.replay().err(),
+            Some(invalid("bidding does not end in a redeal"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_format() -> anyhow::Result<()> {
+        let record = Record::new(&play_round(3, 50, RuleSet::default())?);
+        let text = record.to_string();
+        assert!(text.starts_with("[Players \"a b c d e\"]\n[Seed \"3\"]\n"));
+        assert!(!text.contains("[Rules "));
+        let record = Record::new(&play_round(3, 0, Preset::Simple.into())?);
+        assert!(record.to_string().contains(
+            "[Rules \"same2=false yoromeki=false reverse_jack=false plain_first_trick=false min_bid=13 first_lead=napoleon joker=false players=5\"]\n"
+        ));
+        assert!(text.contains("[Bidding \"a:pass b:13S c:14H d:pass e:pass b:pass\"]\n"));
+        assert!(text.contains("\n1. c:"));
+        assert!(text.contains("\n10. "));
+        assert!(text.contains("[Result \""));
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay_invalid() -> anyhow::Result<()> {
+        let round = play_round(2, 10, RuleSet::default())?;
+        let record = Record::new(&round);
+
+        let mut r = record.clone();
+        r.plays.swap(0, 1);
+        assert!(matches!(r.replay(), Err(Error::OutOfTurn { .. })));
+
+        let mut r = record.clone();
+        r.seed = Some(100);
+        assert!(matches!(r.replay(), Err(Error::InvalidRecord(_))));
+
+        let mut r = record.clone();
+        r.result = Some((Team::Napoleon, 20));
+        assert_eq!(r.replay().err(), Some(Error::NotFinished));
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case("[Seed \"1\"]\n")]
+    #[case("[Players \"a b c\"]\n")]
+    #[case("[Players \"a b c d e\"]\n[Opens \"SA S2\"]\n")]
+    #[case("[Players \"a b c d e\"]\n[Unknown \"1\"]\n")]
+    #[case("[Players a b c d e]\n")]
+    fn test_parse_invalid(#[case] s: &str) {
+        assert!(matches!(s.parse::<Record>(), Err(Error::InvalidRecord(_))));
+    }
+}
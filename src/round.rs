@@ -1,43 +1,437 @@
-use anyhow::Context as _;
+use rand::{Rng as _, SeedableRng as _};
 
+use crate::bidding::{Bid, Bidding, Outcome};
 use crate::card::Card;
-use crate::cards::distribute_cards;
+use crate::card_set::CardSet;
+use crate::cards::Deal;
 use crate::declaration::Declaration;
-use crate::player::{FieldPlayers, Player, Players, Role};
+use crate::error::{Error, Result};
+use crate::event::GameEvent;
+use crate::player::{FieldPlayer, FieldPlayers, Player, Players, Role};
+use crate::rule::{FirstLead, RuleSet};
+use crate::settlement::Settlement;
+use crate::trick::{legal_plays, validate_play, Play, Trick};
 use crate::trick_result::TrickResult;
 
-#[allow(dead_code)]
-#[derive(Debug, PartialEq)]
-enum Team {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Team {
     Napoleon,
     Union,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Phase {
+    Dealing,
+    Bidding,
+    Exchange,
+    Playing { trick_no: u8 },
+    Finished,
+}
+
+/// `Round::undo`で戻せる範囲。
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum UndoPolicy {
+    /// いつでも戻せる
+    #[default]
+    Allowed,
+    /// 今のトリックで出したカードだけ戻せる
+    CurrentTrick,
+    /// 戻せない。レーティング戦で使う
+    Disallowed,
+}
+
+/// JSONでは次の形になる。
+///
+/// - カードは1..=52の番号で、ジョーカーは53 (`Card`を参照)
+/// - `phase`は`"Bidding"`や`{"Playing": {"trick_no": 3}}`
+/// - `face_card_counter`はプレイヤーのidをキーにしたオブジェクトで、キーの順に並ぶ
+/// - `events`は`GameEvent`の配列
+/// - `rules`は`RuleSet`
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct Round {
     pub field_players: FieldPlayers,
-    pub opens: [Card; 2],
+    pub opens: Vec<Card>,
+    seed: Option<u64>,
+    rules: RuleSet,
+    phase: Phase,
+    bidding: Bidding,
     trick_results: Vec<TrickResult>,
+    trick: Trick,
     declaration: Option<Declaration>,
+    discards: Option<Vec<Card>>,
+    #[serde(with = "crate::player::player_map")]
     face_card_counter: std::collections::HashMap<Player, Vec<Card>>,
+    events: Vec<GameEvent>,
+    /// `Round::undo`で戻した操作。新しい順に後ろに積む。
+    redo: Vec<GameEvent>,
+    undo_policy: UndoPolicy,
 }
 
 impl Round {
-    #[allow(dead_code)]
-    pub fn new(players: Players) -> Self {
+    /// `RuleSet::default()`に従って5人で遊ぶ。
+    pub fn new(players: Players) -> Result<Self> {
+        Self::new_with_rng(players, &mut rand::thread_rng())
+    }
+
+    pub fn new_with_rng<R: rand::Rng + ?Sized>(players: Players, rng: &mut R) -> Result<Self> {
+        Self::new_with_seed(players, rng.gen())
+    }
+
+    /// `seed`から配る。同じ`seed`からは同じ手札とオープンになる。
+    pub fn new_with_seed(players: Players, seed: u64) -> Result<Self> {
+        Self::new_with_rules(players, seed, RuleSet::default())
+    }
+
+    /// `rules`に従って遊ぶ。`seed`から配る。
+    /// `rules`が正しくないか、`players`が`RuleSet::players`人でなければ`Error::InvalidRule`を返す。
+    pub fn new_with_rules(players: Players, seed: u64, rules: RuleSet) -> Result<Self> {
+        let mut round = Self::dealing(players, Some(seed), rules)?;
+        round.deal()?;
+        Ok(round)
+    }
+
+    /// 決まった配り方から始める。シードは持たない。
+    pub fn from_deal(players: Players, deal: Deal) -> Result<Self> {
+        Self::from_deal_with_rules(players, deal, RuleSet::default())
+    }
+
+    /// `rules`に従って、決まった配り方から始める。
+    /// 人数、オープンの枚数かジョーカーの有無が`rules`と合わなければ`Error::InvalidEvent`を返す。
+    pub fn from_deal_with_rules(players: Players, deal: Deal, rules: RuleSet) -> Result<Self> {
+        let mut round = Self::dealing(players, None, rules)?;
+        let deal = verified_deal(None, deal, &rules)?;
+        round.set_deal(None, deal);
+        Ok(round)
+    }
+
+    /// `GameEvent`の列から組み立て直す。最初のイベントは`GameEvent::Dealt`でなければならない。
+    pub fn replay<I: IntoIterator<Item = GameEvent>>(events: I) -> Result<Self> {
+        let mut events = events.into_iter();
+        let Some(GameEvent::Dealt {
+            players,
+            seed,
+            rules,
+            deal,
+        }) = events.next()
+        else {
+            return Err(Error::InvalidEvent("first event must be Dealt".to_string()));
+        };
+        if players.0.len() != rules.players {
+            return Err(Error::InvalidEvent(
+                "players do not match the rules".to_string(),
+            ));
+        }
+        let deal = verified_deal(seed, deal, &rules)?;
+        let mut round = Self::dealing(players, seed, rules)?;
+        round.set_deal(seed, deal);
+        for event in events {
+            round.apply(event)?;
+        }
+        Ok(round)
+    }
+
+    fn dealing(players: Players, seed: Option<u64>, rules: RuleSet) -> Result<Self> {
+        rules.validate_for(players.0.len())?;
         let trick_results: Vec<TrickResult> = Vec::new();
-        let (field_players, opens) = distribute_cards(&players);
-        Round {
+        let field_players: FieldPlayers = players
+            .0
+            .iter()
+            .map(|p| FieldPlayer::new(p.clone(), CardSet::new()))
+            .collect::<Vec<FieldPlayer>>()
+            .into();
+        Ok(Round {
             field_players,
-            opens,
+            opens: Vec::new(),
+            seed,
+            rules,
+            phase: Phase::Dealing,
+            bidding: Bidding::with_rules(players.clone(), rules),
             trick_results,
+            trick: Trick::with_size(rules.players),
             declaration: None,
+            discards: None,
             face_card_counter: std::collections::HashMap::new(),
+            events: Vec::new(),
+            redo: Vec::new(),
+            undo_policy: UndoPolicy::default(),
+        })
+    }
+
+    /// これまでに起きたことを順に並べたもの。
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    /// 操作を記録する。新しく操作したら`Round::redo`はできなくなる。
+    fn push_action(&mut self, event: GameEvent) {
+        self.redo.clear();
+        self.events.push(event);
+    }
+
+    pub fn undo_policy(&self) -> UndoPolicy {
+        self.undo_policy
+    }
+
+    pub fn set_undo_policy(&mut self, policy: UndoPolicy) {
+        self.undo_policy = policy;
+    }
+
+    fn ensure_undo(&self, action: &GameEvent) -> Result<()> {
+        let ok = match self.undo_policy {
+            UndoPolicy::Allowed => true,
+            UndoPolicy::CurrentTrick => {
+                matches!(action, GameEvent::Played(_)) && !self.trick.plays.is_empty()
+            }
+            UndoPolicy::Disallowed => false,
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(Error::UndoNotAllowed(self.undo_policy))
+        }
+    }
+
+    /// 最後の操作を取り消す。操作から導かれた配り直しやトリックの勝者も取り消す。
+    pub fn undo(&mut self) -> Result<()> {
+        let last = self.events.iter().rev().find(|e| e.is_action());
+        self.ensure_undo(last.ok_or(Error::NothingToUndo)?)?;
+        self.undo_actions(1)
+    }
+
+    /// 今のトリックで出したカードを全て取り消す。まだ誰も出していなければ、前のトリックを全て取り消す。
+    pub fn undo_trick(&mut self) -> Result<()> {
+        let n = match self.trick.plays.len() {
+            0 if self.trick_results.is_empty() => return Err(Error::NothingToUndo),
+            0 => self.rules.players,
+            n => n,
+        };
+        let last = self.events.iter().rev().find(|e| e.is_action());
+        self.ensure_undo(last.ok_or(Error::NothingToUndo)?)?;
+        self.undo_actions(n)
+    }
+
+    /// 最後の`n`個の操作を取り消して、残りのイベントから一度だけ組み立て直す。
+    /// 最初の`Dealt`がない、古い保存データから移行したラウンドは取り消せない。
+    fn undo_actions(&mut self, n: usize) -> Result<()> {
+        let actions: Vec<usize> = self
+            .events
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, e)| e.is_action())
+            .map(|(i, _)| i)
+            .take(n)
+            .collect();
+        let i = match actions.last() {
+            Some(i) if actions.len() == n => *i,
+            _ => return Err(Error::NothingToUndo),
+        };
+        if !matches!(self.events.first(), Some(GameEvent::Dealt { .. })) {
+            return Err(Error::NothingToUndo);
+        }
+        let mut round = Self::replay(self.events[..i].to_vec())?;
+        round.undo_policy = self.undo_policy;
+        round.redo = std::mem::take(&mut self.redo);
+        round
+            .redo
+            .extend(actions.iter().map(|i| self.events[*i].clone()));
+        *self = round;
+        Ok(())
+    }
+
+    /// `Round::undo`で取り消した操作をやり直す。
+    pub fn redo(&mut self) -> Result<()> {
+        let action = self.redo.last().cloned().ok_or(Error::NothingToRedo)?;
+        let mut redo = std::mem::take(&mut self.redo);
+        let result = self.apply(action);
+        if result.is_ok() {
+            redo.pop();
+        }
+        self.redo = redo;
+        result
+    }
+
+    /// 直前の操作から導かれて、すでに起きているイベントかどうか。
+    fn emitted(&self, event: &GameEvent) -> bool {
+        let start = self
+            .events
+            .iter()
+            .rposition(GameEvent::is_action)
+            .map_or(0, |i| i + 1);
+        self.events[start..].contains(event)
+    }
+
+    /// イベントを1つ適用する。操作はルールに従って検証する。
+    /// 操作から導かれるイベントは、直前の操作ですでに起きていれば何もしない。
+    /// `Dealt`が起きていなければ、その配り方で配り直す。
+    pub fn apply(&mut self, event: GameEvent) -> Result<()> {
+        if !event.is_action() && self.emitted(&event) {
+            return Ok(());
+        }
+        match event {
+            GameEvent::Dealt {
+                players,
+                seed,
+                rules,
+                deal,
+            } => {
+                self.ensure_phase(
+                    "deal",
+                    matches!(self.phase, Phase::Dealing | Phase::Bidding),
+                )?;
+                if players != players_of(&self.field_players) {
+                    return Err(Error::InvalidEvent("players do not match".to_string()));
+                }
+                if rules != self.rules {
+                    return Err(Error::InvalidEvent("rules do not match".to_string()));
+                }
+                let deal = verified_deal(seed, deal, &self.rules)?;
+                self.set_deal(seed, deal);
+                Ok(())
+            }
+            GameEvent::Bid { player, bid } => self.bid(&player, bid),
+            GameEvent::Passed { player } => self.pass(&player),
+            GameEvent::Declared(declaration) => self.set_declaration(declaration),
+            GameEvent::Exchanged { player, discards } => self.exchange(&player, discards),
+            GameEvent::Played(play) => self.play(play),
+            event => Err(Error::InvalidEvent(format!("{:?} did not happen", event))),
+        }
+    }
+
+    /// 今の手札を配ったシード。`Round::from_deal`で始めたときは`None`。
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    pub fn declaration(&self) -> Option<&Declaration> {
+        self.declaration.as_ref()
+    }
+
+    pub fn trick(&self) -> &Trick {
+        &self.trick
+    }
+
+    pub fn trick_results(&self) -> &[TrickResult] {
+        &self.trick_results
+    }
+
+    fn ensure_phase(&self, action: &'static str, ok: bool) -> Result<()> {
+        if ok {
+            Ok(())
+        } else {
+            Err(Error::PhaseViolation {
+                action,
+                phase: self.phase,
+            })
+        }
+    }
+
+    /// カードを配って立ちを決める段階に進む。誰も立たなかったときの配り直しにも使う。
+    /// 配り直すときは今のシードから次のシードを決める。
+    pub fn deal(&mut self) -> Result<()> {
+        self.ensure_phase(
+            "deal",
+            matches!(self.phase, Phase::Dealing | Phase::Bidding),
+        )?;
+        let seed = match self.seed {
+            Some(seed) if self.phase == Phase::Dealing => seed,
+            Some(seed) => rand_chacha::ChaCha8Rng::seed_from_u64(seed).gen(),
+            None => rand::thread_rng().gen(),
+        };
+        let deal = Deal::shuffled_with_rules(
+            &mut rand_chacha::ChaCha8Rng::seed_from_u64(seed),
+            &self.rules,
+        );
+        self.set_deal(Some(seed), deal);
+        Ok(())
+    }
+
+    fn set_deal(&mut self, seed: Option<u64>, deal: Deal) {
+        let players = players_of(&self.field_players);
+        self.field_players = deal.field_players(&players);
+        self.opens = deal.opens.clone();
+        self.seed = seed;
+        self.bidding = Bidding::with_rules(players.clone(), self.rules);
+        self.phase = Phase::Bidding;
+        self.events.push(GameEvent::Dealt {
+            players,
+            seed,
+            rules: self.rules,
+            deal,
+        });
+    }
+
+    pub fn bidding(&self) -> &Bidding {
+        &self.bidding
+    }
+
+    /// 立ちを宣言する。`RuleSet::min_bid`より小さい立ちは宣言できない。
+    pub fn bid(&mut self, player: &Player, bid: Bid) -> Result<()> {
+        self.ensure_phase("bid", self.phase == Phase::Bidding)?;
+        self.bidding.bid(player, bid)?;
+        self.push_action(GameEvent::Bid {
+            player: player.clone(),
+            bid,
+        });
+        Ok(())
+    }
+
+    /// パスする。全員がパスしたら配り直す。
+    pub fn pass(&mut self, player: &Player) -> Result<()> {
+        self.ensure_phase("pass", self.phase == Phase::Bidding)?;
+        self.bidding.pass(player)?;
+        self.push_action(GameEvent::Passed {
+            player: player.clone(),
+        });
+        if self.bidding.outcome() == Some(Outcome::Redeal) {
+            self.deal()?;
+        }
+        Ok(())
+    }
+
+    fn ensure_min_bid(&self, number: usize) -> Result<()> {
+        if !self.rules.bid_numbers().contains(&number) {
+            return Err(Error::InvalidDeclaration(number));
         }
+        Ok(())
     }
 
-    pub fn set_declaration(&mut self, declaration: Declaration) -> anyhow::Result<()> {
-        anyhow::ensure!(self.declaration.is_none(), "Napoleon is already set");
+    /// 競りで決まったナポレオンが副官を指名して宣言する。
+    pub fn declare(&mut self, aide: Card) -> Result<()> {
+        let declaration = self.bidding.declaration(aide)?;
+        self.set_declaration(declaration)
+    }
+
+    /// 宣言する。競りをしたときは、競りで決まったナポレオンと立ちに一致しなければならない。
+    pub fn set_declaration(&mut self, declaration: Declaration) -> Result<()> {
+        self.ensure_phase("declare", self.phase == Phase::Bidding)?;
+        self.ensure_min_bid(declaration.number)?;
+        if !self.bidding.bids().is_empty() {
+            match self.bidding.outcome() {
+                Some(Outcome::Napoleon { player, bid })
+                    if player == declaration.napoleon
+                        && bid.number == declaration.number
+                        && bid.suit == declaration.suit => {}
+                Some(_) => return Err(Error::DeclarationMismatch),
+                None => return Err(Error::NapoleonNotDecided),
+            }
+        }
+        if !self
+            .field_players
+            .0
+            .iter()
+            .any(|p| p.player == declaration.napoleon)
+        {
+            return Err(Error::UnknownPlayer(declaration.napoleon));
+        }
 
         for p in self.field_players.0.iter_mut() {
             if p.player == declaration.napoleon {
@@ -46,38 +440,208 @@ impl Round {
                 p.assign_role(Role::Aide);
             }
         }
-        anyhow::ensure!(
-            self.field_players
-                .0
-                .iter()
-                .any(|p| p.role == Role::Napoleon),
-            "Napoleon is not found"
-        );
+        self.push_action(GameEvent::Declared(declaration.clone()));
         self.declaration = Some(declaration);
+        self.phase = Phase::Exchange;
         Ok(())
     }
 
-    #[allow(dead_code)]
-    fn is_alone(&self) -> bool {
+    /// ナポレオンがオープンを手札に加え、`discard`を捨てる。捨てるのはオープンと同じ枚数。
+    /// ナポレオン以外は交換できない。
+    pub fn exchange(&mut self, player: &Player, discard: Vec<Card>) -> Result<()> {
+        self.ensure_phase("exchange", self.phase == Phase::Exchange)?;
+        let opens = &self.opens;
+        let napoleon = self
+            .field_players
+            .0
+            .iter_mut()
+            .find(|p| p.role == Role::Napoleon)
+            .ok_or(Error::DeclarationNotSet)?;
+        if napoleon.player != *player {
+            return Err(Error::NotNapoleon(player.clone()));
+        }
+        napoleon.choice_opens(opens, &discard)?;
+        self.discards = Some(discard.clone());
+        self.phase = Phase::Playing { trick_no: 1 };
+        self.push_action(GameEvent::Exchanged {
+            player: player.clone(),
+            discards: discard,
+        });
+        Ok(())
+    }
+
+    /// ナポレオンが捨てたカード。
+    pub fn discards(&self) -> Option<&[Card]> {
+        self.discards.as_deref()
+    }
+
+    /// 捨て札の中の絵札。誰の得点にもならない。
+    pub fn discarded_face_cards(&self) -> Vec<Card> {
+        self.discards
+            .iter()
+            .flatten()
+            .filter(|c| c.is_face())
+            .cloned()
+            .collect()
+    }
+
+    /// 副官がいない一人立ちかどうか。
+    pub fn is_alone(&self) -> bool {
         !self.field_players.0.iter().any(|p| p.role == Role::Aide)
     }
 
-    #[allow(dead_code)]
-    fn last_winner(&self) -> anyhow::Result<Player> {
+    fn last_winner(&self) -> Result<Player> {
         Ok(match self.trick_results.last() {
             Some(r) => r.winner.clone(),
-            None => self
-                .field_players
-                .0
-                .iter()
-                .find(|p| p.role == Role::Napoleon)
-                .context("napoleon is not found")?
+            None => {
+                let napoleon = self
+                    .field_players
+                    .0
+                    .iter()
+                    .position(|p| p.role == Role::Napoleon)
+                    .ok_or(Error::DeclarationNotSet)?;
+                let first = match self.rules.first_lead {
+                    FirstLead::Napoleon => napoleon,
+                    FirstLead::NextToNapoleon => (napoleon + 1) % self.rules.players,
+                };
+                self.field_players.0[first].player.clone()
+            }
+        })
+    }
+
+    /// 次にカードを出すプレイヤー。トリックの最初は前のトリックの勝者から。
+    /// 最初のトリックは`RuleSet::first_lead`で決まる(既定ではナポレオン)。
+    pub fn next_player(&self) -> Option<Player> {
+        if !matches!(self.phase, Phase::Playing { .. }) {
+            return None;
+        }
+        let last = match self.trick.last_player() {
+            Some(p) => p,
+            None => return self.last_winner().ok(),
+        };
+        let i = self.field_players.0.iter().position(|p| p.player == last)?;
+        Some(
+            self.field_players.0[(i + 1) % self.rules.players]
                 .player
                 .clone(),
-        })
+        )
+    }
+
+    /// 次に操作するプレイヤー。競りの間は次に宣言するプレイヤー、競りで決まれば宣言と交換をするナポレオン、
+    /// プレイ中は次にカードを出すプレイヤー。
+    pub fn next_actor(&self) -> Option<Player> {
+        match self.phase {
+            Phase::Bidding => match self.bidding.outcome() {
+                Some(Outcome::Napoleon { player, .. }) => Some(player),
+                _ => self.bidding.next_player().cloned(),
+            },
+            Phase::Exchange => self.declaration.as_ref().map(|d| d.napoleon.clone()),
+            Phase::Playing { .. } => self.next_player(),
+            Phase::Dealing | Phase::Finished => None,
+        }
+    }
+
+    /// 副官のカードが場に出ていれば、それを出したプレイヤー。
+    pub fn revealed_aide(&self) -> Option<Player> {
+        let aide = self.declaration.as_ref()?.aide;
+        self.trick_results
+            .iter()
+            .flat_map(|r| r.trick.iter())
+            .chain(self.trick.plays.iter())
+            .find(|p| p.card == aide)
+            .map(|p| p.player.clone())
+    }
+
+    /// 次のプレイヤーが出せるカード。
+    pub fn legal_plays(&self) -> Vec<Card> {
+        let (Phase::Playing { .. }, Some(next)) = (self.phase, self.next_player()) else {
+            return Vec::new();
+        };
+        let trump = self.declaration.as_ref().and_then(|d| d.suit);
+        self.field_players
+            .0
+            .iter()
+            .find(|p| p.player == next)
+            .map(|p| legal_plays(p, &self.trick, trump, &self.rules))
+            .unwrap_or_default()
+    }
+
+    /// 手番、手札、マストフォローを検証してカードを出す。全員が出したらトリックの勝者を決める。
+    /// 手札を出し切ったら精算する。
+    pub fn play(&mut self, play: Play) -> Result<()> {
+        let Phase::Playing { trick_no } = self.phase else {
+            return Err(Error::PhaseViolation {
+                action: "play",
+                phase: self.phase,
+            });
+        };
+        let trump = self.declaration.as_ref().and_then(|d| d.suit);
+        let expected = self.next_player().ok_or(Error::DeclarationNotSet)?;
+        if expected != play.player {
+            return Err(Error::OutOfTurn {
+                expected,
+                actual: play.player,
+            });
+        }
+        let seat = self
+            .field_players
+            .0
+            .iter()
+            .position(|p| p.player == play.player)
+            .ok_or(Error::UnknownPlayer(play.player.clone()))?;
+        validate_play(
+            &self.field_players.0[seat],
+            &self.trick,
+            trump,
+            &self.rules,
+            &play.card,
+        )?;
+        // 失敗しうる計算を先に済ませて、途中までしか進まないようにする
+        let mut trick = self.trick.clone();
+        trick.add(play.clone())?;
+        let result = if trick.is_finished() {
+            Some(TrickResult::new(&trick, trump, trick_no, &self.rules)?)
+        } else {
+            None
+        };
+        let last = usize::from(trick_no) == self.rules.hand_size();
+        let settlement = match &result {
+            Some(result) if last => {
+                let (mut napoleon_score, _) = self.team_score()?;
+                if self
+                    .field_players
+                    .0
+                    .iter()
+                    .any(|p| p.player == result.winner && p.role != Role::Union)
+                {
+                    napoleon_score += result.face_cards.len();
+                }
+                Some(Settlement::with_napoleon_score(self, napoleon_score)?)
+            }
+            _ => None,
+        };
+
+        self.field_players.0[seat].remove(&play.card);
+        self.trick = trick;
+        self.push_action(GameEvent::Played(play));
+        if let Some(result) = result {
+            self.trick = Trick::with_size(self.rules.players);
+            self.events.push(GameEvent::TrickWon(result.clone()));
+            self.add(result);
+            self.phase = if last {
+                Phase::Finished
+            } else {
+                Phase::Playing {
+                    trick_no: trick_no + 1,
+                }
+            };
+        }
+        if let Some(settlement) = settlement {
+            self.events.push(GameEvent::Settled(settlement));
+        }
+        Ok(())
     }
 
-    #[allow(dead_code)]
     fn add(&mut self, result: TrickResult) {
         (*self
             .face_card_counter
@@ -87,11 +651,13 @@ impl Round {
         self.trick_results.push(result);
     }
 
-    #[allow(dead_code)]
-    fn team_score(&self) -> anyhow::Result<(usize, usize)> {
+    /// ナポレオン軍と連合軍がそれぞれ取った絵札の枚数。
+    pub(crate) fn team_score(&self) -> Result<(usize, usize)> {
         let mut napo_score = 0;
         let mut union_score = 0;
-        anyhow::ensure!(!self.face_card_counter.is_empty(), "round is not set yet");
+        if self.declaration.is_none() {
+            return Err(Error::DeclarationNotSet);
+        }
         for (player, face_cards) in &self.face_card_counter {
             let s = face_cards.len();
             let role = self
@@ -99,7 +665,7 @@ impl Round {
                 .0
                 .iter()
                 .find(|p| p.player == *player)
-                .unwrap()
+                .ok_or(Error::UnknownPlayer(player.clone()))?
                 .role
                 .clone();
             match role {
@@ -110,56 +676,642 @@ impl Round {
         Ok((napo_score, union_score))
     }
 
-    #[allow(dead_code)]
-    fn winner(&self) -> anyhow::Result<Option<Team>> {
-        let (napo_score, union_score) = self.team_score()?;
-        let declaration = self
-            .declaration
-            .as_ref()
-            .ok_or(anyhow::anyhow!("declaration is not set"))?;
-        if union_score > 20 - declaration.number {
-            return Ok(Some(Team::Union));
-        }
-        if declaration.number == 20 {
-            return if napo_score == 20 {
-                Ok(Some(Team::Napoleon))
-            } else {
-                Ok(None)
-            };
-        }
-        if napo_score == 20 {
-            return Ok(Some(Team::Union));
-        }
-        if napo_score >= declaration.number {
-            return Ok(Some(Team::Napoleon));
+    /// 今までに取った絵札で勝ち負けが決まっていれば勝った軍。
+    /// ナポレオン軍が立ちに届いたか、もう届かないか、ラウンドが終わったときに`Settlement`と同じ判定をする。
+    pub fn winner(&self) -> Result<Option<Team>> {
+        let (napoleon_score, union_score) = self.team_score()?;
+        let declaration = self.declaration.as_ref().ok_or(Error::DeclarationNotSet)?;
+        let decided = self.phase() == Phase::Finished
+            || napoleon_score >= declaration.number
+            || union_score > 20 - declaration.number;
+        Ok(decided.then(|| {
+            crate::settlement::outcome(
+                declaration.number,
+                declaration.base_score(&self.rules),
+                napoleon_score,
+            )
+            .0
+        }))
+    }
+}
+
+fn players_of(field_players: &FieldPlayers) -> Players {
+    field_players.0.iter().map(|p| p.player.clone()).collect()
+}
+
+/// イベントの配り方を検証する。`rules`で使うカードを配っていて、
+/// シードがあれば、そのシードから配った手札と一致しなければならない。
+fn verified_deal(seed: Option<u64>, deal: Deal, rules: &RuleSet) -> Result<Deal> {
+    let deal = Deal::new(deal.hands, deal.opens)?;
+    let joker = deal
+        .hands
+        .iter()
+        .flatten()
+        .chain(deal.opens.iter())
+        .any(Card::is_joker);
+    if deal.hands.len() != rules.players
+        || deal.opens.len() != rules.opens()
+        || joker != rules.joker
+    {
+        return Err(Error::InvalidEvent(
+            "deal does not match the rules".to_string(),
+        ));
+    }
+    if let Some(seed) = seed {
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(seed);
+        if Deal::shuffled_with_rules(&mut rng, rules) != deal {
+            return Err(Error::InvalidEvent(
+                "deal does not match the seed".to_string(),
+            ));
         }
-        Ok(None)
     }
+    Ok(deal)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rule::Preset;
     use crate::trick::{Play, Trick, TrickArray};
 
     #[test]
-    fn test_round_new() {
-        Round::new(crate::player::Players::default());
+    fn test_round_new() -> anyhow::Result<()> {
+        Round::new(crate::player::Players::default())?;
+        // 人数がルールと合わない
+        assert!(matches!(
+            Round::new(crate::player::Players::with_len(4)),
+            Err(Error::InvalidRule(_))
+        ));
+        let rules = RuleSet {
+            players: 7,
+            ..Default::default()
+        };
+        assert!(matches!(
+            Round::new_with_rules(crate::player::Players::with_len(7), 1, rules),
+            Err(Error::InvalidRule(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_json() -> anyhow::Result<()> {
+        let players = crate::player::Players::default();
+        let mut r = Round::new(players.clone())?;
+        let aide = r.field_players.0[1].hands.to_vec()[0];
+        r.set_declaration(Declaration::new(players.0[0].clone(), None, 13, aide)?)?;
+        r.exchange(&players.0[0], r.opens.clone())?;
+        for _ in 0..12 {
+            let player = r.next_player().unwrap();
+            let card = r.legal_plays()[0];
+            r.play(Play::new(player, card))?;
+        }
+
+        let json = serde_json::to_string(&r)?;
+        let restored: Round = serde_json::from_str(&json)?;
+        assert_eq!(serde_json::to_string(&restored)?, json);
+        assert_eq!(restored.face_card_counter, r.face_card_counter);
+        assert_eq!(restored.trick().plays.len(), 2);
+        assert_eq!(restored.phase(), Phase::Playing { trick_no: 3 });
+        assert_eq!(restored.next_player(), r.next_player());
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_new_with_seed() -> anyhow::Result<()> {
+        let players = crate::player::Players::default();
+        let r1 = Round::new_with_seed(players.clone(), 42)?;
+        let r2 = Round::new_with_seed(players.clone(), 42)?;
+        assert_eq!(r1.seed(), Some(42));
+        assert_eq!(r1.field_players, r2.field_players);
+        assert_eq!(r1.opens, r2.opens);
+
+        let r3 = Round::new_with_rng(players.clone(), &mut rand::thread_rng())?;
+        let r4 = Round::new_with_seed(players.clone(), r3.seed().unwrap())?;
+        assert_eq!(r3.field_players, r4.field_players);
+
+        // 配り直しも再現できる
+        let mut r1 = r1;
+        let mut r2 = r2;
+        r1.deal()?;
+        r2.deal()?;
+        assert_ne!(r1.seed(), Some(42));
+        assert_eq!(r1.seed(), r2.seed());
+        assert_eq!(r1.field_players, r2.field_players);
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_from_deal() -> anyhow::Result<()> {
+        let mut r = fixed_round();
+        assert_eq!(r.phase(), Phase::Bidding);
+        assert_eq!(r.seed(), None);
+        assert!(r.field_players.0[3].has(&Card::try_from(31)?));
+        assert_eq!(r.opens, [Card::try_from(51)?, Card::try_from(52)?]);
+
+        r.deal()?;
+        assert!(r.seed().is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_from_deal_invalid() -> anyhow::Result<()> {
+        // 4人に配ったものを5人で遊ぶ
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(1);
+        let deal = Deal::shuffled_with_rules(&mut rng, &RuleSet::for_players(4));
+        assert!(matches!(
+            Round::from_deal(crate::player::Players::default(), deal),
+            Err(Error::InvalidEvent(_))
+        ));
+        // ジョーカーを使わないルールにジョーカーを配る
+        let rules = RuleSet {
+            joker: true,
+            ..Default::default()
+        };
+        let deal = Deal::shuffled_with_rules(&mut rng, &rules);
+        assert!(matches!(
+            Round::from_deal(crate::player::Players::default(), deal.clone()),
+            Err(Error::InvalidEvent(_))
+        ));
+        Round::from_deal_with_rules(crate::player::Players::default(), deal, rules)?;
+        Ok(())
     }
 
     #[test]
     fn test_set_declaration() -> anyhow::Result<()> {
         let players = crate::player::Players::default();
-        let mut r = Round::new(players.clone());
+        let mut r = Round::new(players.clone())?;
         let d = Declaration::new(players.0[0].clone(), None, 13, Card::try_from(1)?)?;
         r.set_declaration(d)?;
         Ok(())
     }
 
+    #[test]
+    fn test_bidding() -> anyhow::Result<()> {
+        let players = crate::player::Players::default();
+        let mut r = fixed_round();
+        let bid = Bid::new(14, Some(crate::card::Suit::Heart))?;
+        r.bid(&players.0[0], Bid::new(13, None)?)?;
+        r.bid(&players.0[1], bid)?;
+        assert_eq!(
+            r.declare(Card::try_from(1)?),
+            Err(Error::NapoleonNotDecided)
+        );
+        for i in [2, 3, 4, 0] {
+            r.pass(&players.0[i])?;
+        }
+        assert_eq!(r.bidding().highest(), Some((&players.0[1], bid)));
+        assert_eq!(r.next_actor(), Some(players.0[1].clone()));
+
+        let d = Declaration::new(
+            players.0[0].clone(),
+            bid.suit,
+            bid.number,
+            Card::try_from(1)?,
+        )?;
+        assert_eq!(r.set_declaration(d), Err(Error::DeclarationMismatch));
+        r.declare(Card::try_from(1)?)?;
+        assert_eq!(r.phase(), Phase::Exchange);
+        assert_eq!(r.next_actor(), Some(players.0[1].clone()));
+        assert_eq!(r.declaration().unwrap().napoleon, players.0[1]);
+        assert!(r.pass(&players.0[2]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bidding_redeal() -> anyhow::Result<()> {
+        let players = crate::player::Players::default();
+        let mut r = Round::new_with_seed(players.clone(), 42)?;
+        for p in players.0.iter() {
+            r.pass(p)?;
+        }
+        assert_eq!(r.phase(), Phase::Bidding);
+        assert_ne!(r.seed(), Some(42));
+        assert!(r.bidding().bids().is_empty());
+        Ok(())
+    }
+
+    fn played_round(seed: u64) -> anyhow::Result<Round> {
+        let players = crate::player::Players::default();
+        let mut r = Round::new_with_seed(players.clone(), seed)?;
+        // 一度全員パスして配り直す
+        for p in players.0.iter() {
+            r.pass(p)?;
+        }
+        r.bid(&players.0[0], Bid::new(13, None)?)?;
+        for p in players.0[1..].iter() {
+            r.pass(p)?;
+        }
+        r.declare(r.field_players.0[1].hands.to_vec()[0])?;
+        r.exchange(&players.0[0], r.opens.clone())?;
+        while let Some(player) = r.next_player() {
+            let card = r.legal_plays()[0];
+            r.play(Play::new(player, card))?;
+        }
+        Ok(r)
+    }
+
+    #[test]
+    fn test_events() -> anyhow::Result<()> {
+        let r = played_round(1)?;
+        let events = r.events();
+        assert!(matches!(events[0], GameEvent::Dealt { seed: Some(1), .. }));
+        assert!(matches!(events[6], GameEvent::Dealt { .. }));
+        assert_eq!(
+            events
+                .iter()
+                .filter(|e| matches!(e, GameEvent::Played(_)))
+                .count(),
+            50
+        );
+        assert_eq!(
+            events
+                .iter()
+                .filter(|e| matches!(e, GameEvent::TrickWon(_)))
+                .count(),
+            10
+        );
+        assert_eq!(
+            events.last(),
+            Some(&GameEvent::Settled(Settlement::new(&r)?))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay() -> anyhow::Result<()> {
+        let r = played_round(2)?;
+        let json = serde_json::to_string(&r)?;
+        let replayed = Round::replay(r.events().to_vec())?;
+        assert_eq!(serde_json::to_string(&replayed)?, json);
+
+        // 操作だけからでも同じになる
+        let actions = r.events()[..1]
+            .iter()
+            .chain(r.events().iter().filter(|e| e.is_action()))
+            .cloned();
+        let replayed = Round::replay(actions)?;
+        assert_eq!(serde_json::to_string(&replayed)?, json);
+
+        // 途中まで
+        let mut replayed = Round::replay(r.events()[..20].to_vec())?;
+        for event in r.events()[20..].iter() {
+            replayed.apply(event.clone())?;
+        }
+        assert_eq!(serde_json::to_string(&replayed)?, json);
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay_invalid() -> anyhow::Result<()> {
+        let r = played_round(3)?;
+        let events = r.events();
+        assert!(matches!(
+            Round::replay(events[1..].to_vec()),
+            Err(Error::InvalidEvent(_))
+        ));
+
+        let GameEvent::Dealt {
+            players,
+            rules,
+            deal,
+            ..
+        } = events[0].clone()
+        else {
+            unreachable!();
+        };
+        let wrong_seed = GameEvent::Dealt {
+            players: players.clone(),
+            seed: Some(4),
+            rules,
+            deal: deal.clone(),
+        };
+        assert!(matches!(
+            Round::replay([wrong_seed]),
+            Err(Error::InvalidEvent(_))
+        ));
+
+        // 違うルールで配り直す
+        let mut round = Round::replay(events[..1].to_vec())?;
+        let wrong_rules = GameEvent::Dealt {
+            players,
+            seed: Some(3),
+            rules: Preset::Simple.into(),
+            deal,
+        };
+        assert!(matches!(
+            round.apply(wrong_rules),
+            Err(Error::InvalidEvent(_))
+        ));
+
+        // 起きていないトリックの勝者
+        let i = events
+            .iter()
+            .position(|e| matches!(e, GameEvent::TrickWon(_)))
+            .unwrap();
+        let mut replayed = Round::replay(events[..i - 1].to_vec())?;
+        assert!(matches!(
+            replayed.apply(events[i].clone()),
+            Err(Error::InvalidEvent(_))
+        ));
+        assert!(replayed.apply(events[i - 1].clone()).is_ok());
+        assert!(replayed.apply(events[i].clone()).is_ok());
+        Ok(())
+    }
+
+    fn play_n(r: &mut Round, n: usize) -> anyhow::Result<()> {
+        for _ in 0..n {
+            let player = r.next_player().unwrap();
+            let card = r.legal_plays()[0];
+            r.play(Play::new(player, card))?;
+        }
+        Ok(())
+    }
+
+    /// 戻せるかどうか以外の状態。
+    fn state(r: &Round) -> anyhow::Result<serde_json::Value> {
+        let mut value = serde_json::to_value(r)?;
+        value.as_object_mut().unwrap().remove("redo");
+        Ok(value)
+    }
+
+    fn started_round() -> anyhow::Result<Round> {
+        let mut r = fixed_round();
+        let napoleon = r.field_players.0[0].player.clone();
+        let aide = Card::try_from(11)?;
+        r.set_declaration(Declaration::new(napoleon.clone(), None, 13, aide)?)?;
+        r.exchange(&napoleon, r.opens.clone())?;
+        Ok(r)
+    }
+
+    #[test]
+    fn test_undo() -> anyhow::Result<()> {
+        assert_eq!(fixed_round().undo(), Err(Error::NothingToUndo));
+        let mut r = started_round()?;
+        play_n(&mut r, 7)?;
+        let before = state(&r)?;
+        play_n(&mut r, 1)?;
+        let after = state(&r)?;
+
+        r.undo()?;
+        assert_eq!(state(&r)?, before);
+        r.redo()?;
+        assert_eq!(state(&r)?, after);
+        assert_eq!(r.redo(), Err(Error::NothingToRedo));
+
+        // トリックの勝者も取り消す
+        play_n(&mut r, 1)?;
+        let before = state(&r)?;
+        play_n(&mut r, 1)?;
+        assert_eq!(r.trick_results().len(), 2);
+        r.undo()?;
+        assert_eq!(state(&r)?, before);
+        assert_eq!(r.trick_results().len(), 1);
+        assert_eq!(r.trick().plays.len(), 4);
+
+        // 新しく操作したらやり直せない
+        r.undo()?;
+        play_n(&mut r, 1)?;
+        assert_eq!(r.redo(), Err(Error::NothingToRedo));
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_trick() -> anyhow::Result<()> {
+        let mut r = started_round()?;
+        let start = state(&r)?;
+        play_n(&mut r, 5)?;
+        let first = state(&r)?;
+        play_n(&mut r, 2)?;
+
+        r.undo_trick()?;
+        assert_eq!(state(&r)?, first);
+        r.undo_trick()?;
+        assert_eq!(state(&r)?, start);
+        assert_eq!(r.undo_trick(), Err(Error::NothingToUndo));
+        for _ in 0..7 {
+            r.redo()?;
+        }
+        assert_eq!(r.trick().plays.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_policy() -> anyhow::Result<()> {
+        let mut r = started_round()?;
+        r.set_undo_policy(UndoPolicy::CurrentTrick);
+        assert_eq!(
+            r.undo(),
+            Err(Error::UndoNotAllowed(UndoPolicy::CurrentTrick))
+        );
+        play_n(&mut r, 6)?;
+        r.undo()?;
+        assert_eq!(r.undo_policy(), UndoPolicy::CurrentTrick);
+        assert_eq!(
+            r.undo(),
+            Err(Error::UndoNotAllowed(UndoPolicy::CurrentTrick))
+        );
+        assert_eq!(
+            r.undo_trick(),
+            Err(Error::UndoNotAllowed(UndoPolicy::CurrentTrick))
+        );
+
+        r.set_undo_policy(UndoPolicy::Disallowed);
+        play_n(&mut r, 1)?;
+        assert_eq!(r.undo(), Err(Error::UndoNotAllowed(UndoPolicy::Disallowed)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_redeal() -> anyhow::Result<()> {
+        let players = crate::player::Players::default();
+        let mut r = Round::new_with_seed(players.clone(), 42)?;
+        let dealt = r.field_players.clone();
+        for p in players.0.iter() {
+            r.pass(p)?;
+        }
+        assert_ne!(r.field_players, dealt);
+        r.undo()?;
+        assert_eq!(r.seed(), Some(42));
+        assert_eq!(r.field_players, dealt);
+        assert_eq!(r.bidding().bids().len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_phase() -> anyhow::Result<()> {
+        let players = crate::player::Players::default();
+        let mut r = Round::new(players.clone())?;
+        assert_eq!(r.phase(), Phase::Bidding);
+        assert!(r.exchange(&players.0[0], r.opens.clone()).is_err());
+        r.deal()?;
+        assert_eq!(r.phase(), Phase::Bidding);
+
+        let d = Declaration::new(players.0[0].clone(), None, 13, Card::try_from(1)?)?;
+        r.set_declaration(d)?;
+        assert_eq!(r.phase(), Phase::Exchange);
+        assert!(r.deal().is_err());
+
+        r.exchange(&players.0[0], r.opens.clone())?;
+        assert_eq!(r.phase(), Phase::Playing { trick_no: 1 });
+        assert!(r.exchange(&players.0[0], r.opens.clone()).is_err());
+        assert_eq!(
+            r.deal(),
+            Err(Error::PhaseViolation {
+                action: "deal",
+                phase: Phase::Playing { trick_no: 1 },
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_exchange() -> anyhow::Result<()> {
+        let players = crate::player::Players::default();
+        let mut r = fixed_round();
+        let d = Declaration::new(players.0[0].clone(), None, 13, Card::try_from(11)?)?;
+        r.set_declaration(d)?;
+        let card = |id: u8| Card::try_from(id).unwrap();
+
+        assert_eq!(
+            r.exchange(&players.0[1], vec![card(11), card(12)]),
+            Err(Error::NotNapoleon(players.0[1].clone()))
+        );
+        assert_eq!(
+            r.exchange(&players.0[0], vec![card(11), card(1)]),
+            Err(Error::NotInHands(card(11)))
+        );
+        assert_eq!(
+            r.exchange(&players.0[0], vec![card(1), card(1)]),
+            Err(Error::DuplicatedCard(card(1)))
+        );
+        assert_eq!(
+            r.exchange(&players.0[0], vec![card(1)]),
+            Err(Error::InvalidHandsSize {
+                expected: 10,
+                actual: 11
+            })
+        );
+        assert_eq!(r.phase(), Phase::Exchange);
+        assert_eq!(r.discards(), None);
+
+        r.exchange(&players.0[0], vec![card(1), card(2)])?;
+        assert_eq!(r.phase(), Phase::Playing { trick_no: 1 });
+        assert_eq!(r.discards(), Some(&[card(1), card(2)][..]));
+        assert_eq!(r.discarded_face_cards(), vec![card(1)]);
+        let napoleon = &r.field_players.0[0];
+        assert_eq!(napoleon.hands.len(), 10);
+        assert!(napoleon.has(&card(51)) && napoleon.has(&card(52)));
+        assert!(!napoleon.has(&card(1)) && !napoleon.has(&card(2)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_play_all_tricks() -> anyhow::Result<()> {
+        let players = crate::player::Players::default();
+        let mut r = Round::new(players.clone())?;
+        let d = Declaration::new(
+            players.0[0].clone(),
+            Some(crate::card::Suit::Spade),
+            13,
+            Card::try_from(1)?,
+        )?;
+        r.set_declaration(d)?;
+        r.exchange(&players.0[0], r.opens.clone())?;
+        while let Some(player) = r.next_player() {
+            let card = r.legal_plays()[0];
+            r.play(Play::new(player, card))?;
+        }
+        assert_eq!(r.phase(), Phase::Finished);
+        assert_eq!(r.trick_results().len(), 10);
+        assert!(r.field_players.0.iter().all(|p| p.hands.is_empty()));
+        assert!(r.legal_plays().is_empty());
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(1)]
+    #[case(2)]
+    #[case(3)]
+    fn test_play_with_joker(#[case] seed: u64) -> anyhow::Result<()> {
+        let players = crate::player::Players::default();
+        let rules = RuleSet {
+            joker: true,
+            ..Default::default()
+        };
+        let mut r = Round::new_with_rules(players.clone(), seed, rules)?;
+        assert_eq!(r.opens.len(), 3);
+        let aide = r.field_players.0[1].hands.to_vec()[0];
+        r.set_declaration(Declaration::new(players.0[0].clone(), None, 13, aide)?)?;
+        r.exchange(&players.0[0], r.opens.clone())?;
+        while let Some(player) = r.next_player() {
+            let card = r.legal_plays()[0];
+            r.play(Play::new(player, card))?;
+        }
+        assert_eq!(r.phase(), Phase::Finished);
+        // ジョーカーは絵札に数えない
+        let faces: usize = r.trick_results().iter().map(|t| t.face_cards.len()).sum();
+        assert_eq!(faces + r.discarded_face_cards().len(), 20);
+
+        let replayed = Round::replay(r.events().to_vec())?;
+        assert_eq!(replayed.trick_results(), r.trick_results());
+
+        // ジョーカーのない配り方はジョーカーを使うルールに合わない
+        let mut events = Round::new_with_seed(players, seed)?.events().to_vec();
+        if let GameEvent::Dealt { rules: r, .. } = &mut events[0] {
+            *r = rules;
+        }
+        assert!(matches!(Round::replay(events), Err(Error::InvalidEvent(_))));
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(4, false, 12, 4)]
+    #[case(6, false, 8, 4)]
+    #[case(6, true, 8, 5)]
+    fn test_play_with_players(
+        #[case] n: usize,
+        #[case] joker: bool,
+        #[case] hand_size: usize,
+        #[case] opens: usize,
+    ) -> anyhow::Result<()> {
+        let players = crate::player::Players::with_len(n);
+        let rules = RuleSet {
+            joker,
+            ..RuleSet::for_players(n)
+        };
+        let mut r = Round::new_with_rules(players.clone(), 1, rules)?;
+        assert_eq!(r.opens.len(), opens);
+        assert!(r.field_players.0.iter().all(|p| p.hands.len() == hand_size));
+        let aide = r.field_players.0[1].hands.to_vec()[0];
+        let d = Declaration::with_rules(players.0[0].clone(), None, rules.min_bid, aide, &rules)?;
+        r.set_declaration(d)?;
+        r.exchange(&players.0[0], r.opens.clone())?;
+        while let Some(player) = r.next_player() {
+            let card = r.legal_plays()[0];
+            r.play(Play::new(player, card))?;
+        }
+        assert_eq!(r.phase(), Phase::Finished);
+        assert_eq!(r.trick_results().len(), hand_size);
+        assert!(r.trick_results().iter().all(|t| t.trick.len() == n));
+
+        let replayed = Round::replay(r.events().to_vec())?;
+        assert_eq!(replayed.trick_results(), r.trick_results());
+
+        // 人数がルールと合わない配り方は再現できない
+        let mut events = r.events().to_vec();
+        if let GameEvent::Dealt { rules: r, .. } = &mut events[0] {
+            *r = RuleSet::default();
+        }
+        assert!(matches!(Round::replay(events), Err(Error::InvalidEvent(_))));
+        Ok(())
+    }
+
     #[test]
     fn test_set_declaration_invalid_napoleon() -> anyhow::Result<()> {
         let players = crate::player::Players::default();
-        let mut r = Round::new(players.clone());
+        let mut r = Round::new(players.clone())?;
         let d = Declaration::new(players.0[0].clone(), None, 13, Card::try_from(1)?)?;
         r.set_declaration(d)?;
         Ok(())
@@ -168,7 +1320,7 @@ mod tests {
     #[test]
     fn test_set_declaration_already_set() -> anyhow::Result<()> {
         let players = crate::player::Players::default();
-        let mut r = Round::new(players.clone());
+        let mut r = Round::new(players.clone())?;
         let d = Declaration::new(players.0[0].clone(), None, 13, Card::try_from(1)?)?;
         r.set_declaration(d)?;
 
@@ -180,22 +1332,22 @@ mod tests {
     #[test]
     fn test_is_alone() -> anyhow::Result<()> {
         let players = crate::player::Players::default();
-        let mut r = Round::new(players.clone());
+        let mut r = Round::new(players.clone())?;
         let d = Declaration::new(
             r.field_players.0[0].player.clone(),
             None,
             13,
-            r.field_players.0[0].hands[0],
+            r.field_players.0[0].hands.to_vec()[0],
         )?;
         r.set_declaration(d)?;
         assert!(r.is_alone());
 
-        let mut r = Round::new(players.clone());
+        let mut r = Round::new(players.clone())?;
         let d = Declaration::new(
             r.field_players.0[0].player.clone(),
             None,
             13,
-            r.field_players.0[1].hands[0],
+            r.field_players.0[1].hands.to_vec()[0],
         )?;
         r.set_declaration(d)?;
         assert!(!r.is_alone());
@@ -203,9 +1355,11 @@ mod tests {
     }
 
     fn dummy_trick(field_players: FieldPlayers, i: usize) -> TrickArray {
-        let mut trick = Trick::new();
+        let mut trick = Trick::new(&RuleSet::default());
         for p in field_players.0 {
-            trick.add(Play::new(p.player.clone(), p.hands[i]));
+            trick
+                .add(Play::new(p.player.clone(), p.hands.to_vec()[i]))
+                .unwrap();
         }
         trick.array().unwrap()
     }
@@ -213,12 +1367,12 @@ mod tests {
     #[test]
     fn test_add() -> anyhow::Result<()> {
         let players = crate::player::Players::default();
-        let mut r = Round::new(players.clone());
+        let mut r = Round::new(players.clone())?;
         let d = Declaration::new(
             r.field_players.0[0].player.clone(),
             None,
             13,
-            r.field_players.0[1].hands[0],
+            r.field_players.0[1].hands.to_vec()[0],
         )?;
         r.set_declaration(d)?;
 
@@ -236,14 +1390,14 @@ mod tests {
     #[test]
     fn test_team_score() -> anyhow::Result<()> {
         let players = crate::player::Players::default();
-        let mut r = Round::new(players.clone());
+        let mut r = Round::new(players.clone())?;
         assert!(r.team_score().is_err());
 
         let d = Declaration::new(
             r.field_players.0[0].player.clone(),
             None,
             13,
-            r.field_players.0[1].hands[0],
+            r.field_players.0[1].hands.to_vec()[0],
         )?;
         r.set_declaration(d)?;
 
@@ -275,14 +1429,14 @@ mod tests {
     #[test]
     fn test_winner_napoleon() -> anyhow::Result<()> {
         let players = crate::player::Players::default();
-        let mut r = Round::new(players.clone());
+        let mut r = Round::new(players.clone())?;
         assert!(r.winner().is_err());
 
         let d = Declaration::new(
             r.field_players.0[0].player.clone(),
             None,
             13,
-            r.field_players.0[1].hands[0],
+            r.field_players.0[1].hands.to_vec()[0],
         )?;
         r.set_declaration(d)?;
 
@@ -319,14 +1473,14 @@ mod tests {
     #[test]
     fn test_winner_union() -> anyhow::Result<()> {
         let players = crate::player::Players::default();
-        let mut r = Round::new(players.clone());
+        let mut r = Round::new(players.clone())?;
         assert!(r.winner().is_err());
 
         let d = Declaration::new(
             r.field_players.0[0].player.clone(),
             None,
             13,
-            r.field_players.0[1].hands[0],
+            r.field_players.0[1].hands.to_vec()[0],
         )?;
         r.set_declaration(d)?;
 
@@ -353,14 +1507,14 @@ mod tests {
     #[test]
     fn test_last_winner() -> anyhow::Result<()> {
         let players = crate::player::Players::default();
-        let mut r = Round::new(players.clone());
+        let mut r = Round::new(players.clone())?;
         assert!(r.last_winner().is_err());
 
         let d = Declaration::new(
             r.field_players.0[0].player.clone(),
             None,
             13,
-            r.field_players.0[1].hands[0],
+            r.field_players.0[1].hands.to_vec()[0],
         )?;
         r.set_declaration(d)?;
         assert_eq!(
@@ -379,4 +1533,114 @@ mod tests {
         );
         Ok(())
     }
+
+    fn fixed_round() -> Round {
+        let hands = (0..5)
+            .map(|i| {
+                (1..=10)
+                    .map(|j| Card::try_from((i * 10 + j) as u8).unwrap())
+                    .collect()
+            })
+            .collect();
+        let opens = vec![Card::try_from(51).unwrap(), Card::try_from(52).unwrap()];
+        Round::from_deal(
+            crate::player::Players::default(),
+            Deal::new(hands, opens).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_play() -> anyhow::Result<()> {
+        let players = crate::player::Players::default();
+        let mut r = fixed_round();
+        let play = |i: usize, id: u8| Play::new(players.0[i].clone(), Card::try_from(id).unwrap());
+        assert_eq!(
+            r.play(play(0, 2)),
+            Err(Error::PhaseViolation {
+                action: "play",
+                phase: Phase::Bidding,
+            })
+        );
+
+        let d = Declaration::new(players.0[0].clone(), None, 13, Card::try_from(11)?)?;
+        r.set_declaration(d)?;
+        assert_eq!(
+            r.play(play(0, 2)),
+            Err(Error::PhaseViolation {
+                action: "play",
+                phase: Phase::Exchange,
+            })
+        );
+        r.exchange(
+            &players.0[0],
+            vec![Card::try_from(51)?, Card::try_from(52)?],
+        )?;
+        assert_eq!(r.next_player(), Some(players.0[0].clone()));
+        assert_eq!(
+            r.play(play(1, 11)),
+            Err(Error::OutOfTurn {
+                expected: players.0[0].clone(),
+                actual: players.0[1].clone(),
+            })
+        );
+        assert_eq!(
+            r.play(play(0, 11)),
+            Err(Error::NotInHands(Card::try_from(11)?))
+        );
+
+        r.play(play(0, 2))?;
+        assert_eq!(r.next_player(), Some(players.0[1].clone()));
+        assert_eq!(
+            r.play(play(1, 14)),
+            Err(Error::IllegalPlay {
+                card: Card::try_from(14)?,
+                lead: crate::card::Suit::Spade,
+            })
+        );
+        r.play(play(1, 13))?;
+        r.play(play(2, 21))?;
+        r.play(play(3, 31))?;
+        r.play(play(4, 41))?;
+
+        assert_eq!(r.trick_results.len(), 1);
+        assert_eq!(r.phase(), Phase::Playing { trick_no: 2 });
+        assert_eq!(r.trick_results[0].winner, players.0[1]);
+        assert!(r.trick.plays.is_empty());
+        assert_eq!(r.next_player(), Some(players.0[1].clone()));
+        assert_eq!(r.field_players.0[0].hands.len(), 9);
+        assert!(!r.field_players.0[1].has(&Card::try_from(13)?));
+        Ok(())
+    }
+
+    #[test]
+    fn test_play_last_trick_error() -> anyhow::Result<()> {
+        let players = crate::player::Players::default();
+        let mut r = Round::new_with_seed(players.clone(), 1)?;
+        let aide = r.field_players.0[1].hands.to_vec()[0];
+        r.set_declaration(Declaration::new(players.0[0].clone(), None, 13, aide)?)?;
+        r.exchange(&players.0[0], r.opens.clone())?;
+        while r.phase() != (Phase::Playing { trick_no: 10 }) || r.trick.plays.len() < 4 {
+            let player = r.next_player().unwrap();
+            r.play(Play::new(player, r.legal_plays()[0]))?;
+        }
+        // 精算できなければ、最後のカードを出す前のまま
+        let unknown = crate::player::Player {
+            id: "z".to_string(),
+        };
+        r.face_card_counter.insert(unknown.clone(), Vec::new());
+        let player = r.next_player().unwrap();
+        let card = r.legal_plays()[0];
+        let events = r.events().len();
+        assert_eq!(
+            r.play(Play::new(player.clone(), card)),
+            Err(Error::UnknownPlayer(unknown))
+        );
+        assert_eq!(r.phase(), Phase::Playing { trick_no: 10 });
+        assert_eq!(r.trick.plays.len(), 4);
+        assert_eq!(r.events().len(), events);
+        assert_eq!(r.trick_results.len(), 9);
+        assert!(r.field_players.0.iter().any(|p| p.has(&card)));
+        Ok(())
+    }
 }
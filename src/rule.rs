@@ -0,0 +1,387 @@
+//! ハウスルールの違い。
+//!
+//! JSONでは書かなかった項目が`RuleSet::default()`の値になる。
+//! 4人や6人で遊ぶときは`RuleSet::for_players`から始める。
+//! 棋譜などでは`same2=false min_bid=14`のように`名前=値`を空白で区切って書き、書かなかった項目は既定の値になる。
+//! `min_bid`を書かなければ人数で決まる下限になる。
+use crate::card::{Card, Suit};
+use crate::card_set::CardSet;
+use crate::error::{Error, Result};
+
+/// 最初のトリックで最初に出す人。2トリック目からは前のトリックの勝者が出す。
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum FirstLead {
+    #[default]
+    Napoleon,
+    /// ナポレオンの次の席
+    NextToNapoleon,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct RuleSet {
+    /// 2トリック目以降、全員が台札と同じスートを出せば2が勝つ
+    pub same2: bool,
+    /// よろめきがマイティに勝つ
+    pub yoromeki: bool,
+    /// 裏ジャックが正ジャックの次に強い
+    pub reverse_jack: bool,
+    /// 最初のトリックではマイティ、ジャック、よろめきがただのカードとして強さを比べる。マストフォローの例外のまま
+    pub plain_first_trick: bool,
+    /// 宣言できる一番小さい立ち。`RuleSet::lowest_bid`より小さくはできない
+    pub min_bid: usize,
+    pub first_lead: FirstLead,
+    /// ジョーカーを加えた53枚で遊ぶ。オープンが1枚増える
+    pub joker: bool,
+    /// 遊ぶ人数。4人から6人
+    pub players: usize,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet {
+            same2: true,
+            yoromeki: true,
+            reverse_jack: true,
+            plain_first_trick: false,
+            min_bid: 13,
+            first_lead: FirstLead::Napoleon,
+            joker: false,
+            players: 5,
+        }
+    }
+}
+
+/// 名前のついたルールの組み合わせ。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Preset {
+    /// `RuleSet::default()`
+    Standard,
+    /// 2、よろめき、裏ジャックを使わない
+    Simple,
+    /// 最初のトリックで役札を使わず、ナポレオンの次の席から出す
+    Classic,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::Standard, Preset::Simple, Preset::Classic];
+}
+
+impl From<Preset> for RuleSet {
+    fn from(preset: Preset) -> Self {
+        let standard = RuleSet::default();
+        match preset {
+            Preset::Standard => standard,
+            Preset::Simple => RuleSet {
+                same2: false,
+                yoromeki: false,
+                reverse_jack: false,
+                ..standard
+            },
+            Preset::Classic => RuleSet {
+                plain_first_trick: true,
+                first_lead: FirstLead::NextToNapoleon,
+                ..standard
+            },
+        }
+    }
+}
+
+impl RuleSet {
+    /// `players`人で遊ぶ既定のルール。立ちの下限も人数に合わせる。
+    pub fn for_players(players: usize) -> Self {
+        let rules = RuleSet {
+            players,
+            ..Default::default()
+        };
+        RuleSet {
+            min_bid: rules.lowest_bid(),
+            ..rules
+        }
+    }
+
+    /// `players`が4から6の間にあり、`min_bid`が人数で決まる下限から20の間にあることを検証する。
+    pub fn validate(&self) -> Result<()> {
+        self.validate_players()?;
+        if !(self.lowest_bid()..=20).contains(&self.min_bid) {
+            return Err(Error::InvalidRule(format!("min_bid {}", self.min_bid)));
+        }
+        Ok(())
+    }
+
+    /// `validate`に加えて、`n`人がこのルールの人数と合うことを検証する。
+    pub fn validate_for(&self, n: usize) -> Result<()> {
+        self.validate()?;
+        if n != self.players {
+            return Err(Error::InvalidRule(format!(
+                "{} players for players={}",
+                n, self.players
+            )));
+        }
+        Ok(())
+    }
+
+    fn validate_players(&self) -> Result<()> {
+        if !(4..=6).contains(&self.players) {
+            return Err(Error::InvalidRule(format!("players {}", self.players)));
+        }
+        Ok(())
+    }
+
+    /// 1人に配る枚数。トリックの数でもある。人数が4から6の間になければ0。
+    pub fn hand_size(&self) -> usize {
+        match self.players {
+            4 => 12,
+            5 => 10,
+            6 => 8,
+            _ => 0,
+        }
+    }
+
+    /// 人数で決まる立ちの下限。4人では14、5人では13、6人では12。
+    pub fn lowest_bid(&self) -> usize {
+        18usize.saturating_sub(self.players)
+    }
+
+    /// 宣言できる立ちの枚数。`min_bid`から20。
+    pub fn bid_numbers(&self) -> std::ops::RangeInclusive<usize> {
+        self.min_bid..=*crate::bidding::Bid::NUMBERS.end()
+    }
+
+    /// `n_round`トリック目でマイティ、ジャック、よろめきが役札として働くかどうか。
+    pub fn specials(&self, n_round: u8) -> bool {
+        !(self.plain_first_trick && n_round == 1)
+    }
+
+    /// 使うカード。
+    pub fn deck(&self) -> CardSet {
+        if self.joker {
+            CardSet::ALL | CardSet::JOKER
+        } else {
+            CardSet::ALL
+        }
+    }
+
+    /// オープンの枚数。手札に配った残り。
+    pub fn opens(&self) -> usize {
+        self.deck()
+            .len()
+            .saturating_sub(self.players * self.hand_size())
+    }
+
+    /// マストフォローの例外としていつでも出せるカード。マイティ、正ジャック、裏ジャック、ジョーカー。
+    pub fn free_cards(&self, trump: Option<Suit>) -> CardSet {
+        let mut cards = self.deck() & CardSet::JOKER;
+        cards.insert(Card {
+            number: 1,
+            suit: Suit::Spade,
+        });
+        if let Some(s) = trump {
+            cards.insert(Card {
+                number: 11,
+                suit: s,
+            });
+            if self.reverse_jack {
+                cards.insert(Card {
+                    number: 11,
+                    suit: s.reverse(),
+                });
+            }
+        }
+        cards
+    }
+}
+
+impl std::fmt::Display for RuleSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let first_lead = match self.first_lead {
+            FirstLead::Napoleon => "napoleon",
+            FirstLead::NextToNapoleon => "next",
+        };
+        write!(
+            f,
+            "same2={} yoromeki={} reverse_jack={} plain_first_trick={} min_bid={} first_lead={} joker={} players={}",
+            self.same2,
+            self.yoromeki,
+            self.reverse_jack,
+            self.plain_first_trick,
+            self.min_bid,
+            first_lead,
+            self.joker,
+            self.players
+        )
+    }
+}
+
+impl std::str::FromStr for RuleSet {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut rules = RuleSet::default();
+        let mut min_bid = None;
+        for item in s.split_whitespace() {
+            let invalid = || Error::InvalidNotation(item.to_string());
+            let (name, value) = item.split_once('=').ok_or_else(invalid)?;
+            let flag = || value.parse::<bool>().map_err(|_| invalid());
+            match name {
+                "same2" => rules.same2 = flag()?,
+                "yoromeki" => rules.yoromeki = flag()?,
+                "reverse_jack" => rules.reverse_jack = flag()?,
+                "plain_first_trick" => rules.plain_first_trick = flag()?,
+                "joker" => rules.joker = flag()?,
+                "min_bid" => min_bid = Some(value.parse().map_err(|_| invalid())?),
+                "players" => rules.players = value.parse().map_err(|_| invalid())?,
+                "first_lead" => {
+                    rules.first_lead = match value {
+                        "napoleon" => FirstLead::Napoleon,
+                        "next" => FirstLead::NextToNapoleon,
+                        _ => return Err(invalid()),
+                    }
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        // 立ちの下限は人数で決まるので、先に人数を検証する
+        rules.validate_players()?;
+        rules.min_bid = min_bid.unwrap_or(rules.lowest_bid());
+        rules.validate()?;
+        Ok(rules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rstest::rstest]
+    #[test]
+    #[case(Preset::Standard)]
+    #[case(Preset::Simple)]
+    #[case(Preset::Classic)]
+    fn test_notation(#[case] preset: Preset) -> anyhow::Result<()> {
+        let rules = RuleSet::from(preset);
+        rules.validate()?;
+        assert_eq!(rules.to_string().parse::<RuleSet>()?, rules);
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case("", RuleSet::default())]
+    #[case("same2=false yoromeki=false reverse_jack=false", Preset::Simple.into())]
+    #[case("first_lead=next plain_first_trick=true", Preset::Classic.into())]
+    #[case("min_bid=15", RuleSet { min_bid: 15, ..Default::default() })]
+    #[case("joker=true", RuleSet { joker: true, ..Default::default() })]
+    #[case("players=4", RuleSet::for_players(4))]
+    #[case("players=6 min_bid=13", RuleSet { min_bid: 13, ..RuleSet::for_players(6) })]
+    fn test_parse(#[case] s: &str, #[case] expected: RuleSet) -> anyhow::Result<()> {
+        assert_eq!(s.parse::<RuleSet>()?, expected);
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case("same2")]
+    #[case("same2=yes")]
+    #[case("jokers=true")]
+    #[case("first_lead=last")]
+    #[case("min_bid=12")]
+    #[case("players=3")]
+    #[case("players=7")]
+    #[case("players=20")]
+    #[case("players=4 min_bid=13")]
+    fn test_parse_invalid(#[case] s: &str) {
+        assert!(s.parse::<RuleSet>().is_err());
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(0)]
+    #[case(3)]
+    #[case(7)]
+    #[case(20)]
+    fn test_invalid_players(#[case] players: usize) {
+        // 検証する前に人数から決まる値を求めても溢れない
+        let rules = RuleSet {
+            players,
+            ..Default::default()
+        };
+        assert_eq!(rules.hand_size(), 0);
+        assert_eq!(rules.opens(), 52);
+        assert!(rules.lowest_bid() <= 18);
+        assert!(matches!(rules.validate(), Err(Error::InvalidRule(_))));
+    }
+
+    #[test]
+    fn test_json() -> anyhow::Result<()> {
+        let rules: RuleSet = serde_json::from_str(r#"{"same2": false, "min_bid": 14}"#)?;
+        assert_eq!(
+            rules,
+            RuleSet {
+                same2: false,
+                min_bid: 14,
+                ..Default::default()
+            }
+        );
+        let json = serde_json::to_string(&RuleSet::from(Preset::Classic))?;
+        assert_eq!(
+            serde_json::from_str::<RuleSet>(&json)?,
+            Preset::Classic.into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_free_cards() -> anyhow::Result<()> {
+        let standard = RuleSet::default();
+        let simple = RuleSet::from(Preset::Simple);
+        assert_eq!(standard.free_cards(None), "SA".parse()?);
+        assert_eq!(standard.free_cards(Some(Suit::Heart)), "SA HJ DJ".parse()?);
+        assert_eq!(simple.free_cards(Some(Suit::Heart)), "SA HJ".parse()?);
+        let joker = RuleSet {
+            joker: true,
+            ..Default::default()
+        };
+        assert_eq!(joker.free_cards(None), "SA JK".parse()?);
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(5, false, 52, 2)]
+    #[case(5, true, 53, 3)]
+    #[case(4, false, 52, 4)]
+    #[case(6, false, 52, 4)]
+    #[case(6, true, 53, 5)]
+    fn test_deck(
+        #[case] players: usize,
+        #[case] joker: bool,
+        #[case] cards: usize,
+        #[case] opens: usize,
+    ) {
+        let rules = RuleSet {
+            joker,
+            ..RuleSet::for_players(players)
+        };
+        assert_eq!(rules.deck().len(), cards);
+        assert_eq!(rules.opens(), opens);
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(4, 12, 14)]
+    #[case(5, 10, 13)]
+    #[case(6, 8, 12)]
+    fn test_for_players(
+        #[case] players: usize,
+        #[case] hand_size: usize,
+        #[case] min_bid: usize,
+    ) -> anyhow::Result<()> {
+        let rules = RuleSet::for_players(players);
+        rules.validate()?;
+        assert_eq!(rules.hand_size(), hand_size);
+        assert_eq!(rules.min_bid, min_bid);
+        assert_eq!(RuleSet::for_players(5), RuleSet::default());
+        Ok(())
+    }
+}
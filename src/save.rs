@@ -0,0 +1,331 @@
+//! `Game`の保存形式。
+//!
+//! `{"version": 1, "game": {...}}`の形で保存し、読み込むときは古いバージョンから順に移行する。
+//! バイナリはCBORで、JSONと同じ形を持つ。
+//!
+//! - version 0: バージョンを持たない`Game`そのもの
+//! - version 1: `version`と`game`を持つ
+//! - version 2: `Round`が競りの経過`bidding`を持つ
+//! - version 3: `Round`がイベントの列`events`を持つ。移行したラウンドの`events`は空になる
+//! - version 4: `Round`が取り消した操作`redo`と`undo_policy`を持つ
+//! - version 5: `Game`、`Round`と`GameEvent::Dealt`がルール`rules`を持つ
+//! - version 6: `Bidding`がルール`rules`を持つ
+use crate::error::{Error, Result};
+use crate::game::Game;
+use crate::rule::RuleSet;
+use serde_json::Value;
+
+pub const VERSION: u32 = 6;
+
+/// `MIGRATIONS[n]`はversion nの保存データをversion n + 1に移行する。
+const MIGRATIONS: [fn(Value) -> Result<Value>; VERSION as usize] = [
+    migrate_v0, migrate_v1, migrate_v2, migrate_v3, migrate_v4, migrate_v5,
+];
+
+#[derive(serde::Serialize)]
+struct Envelope<'a> {
+    version: u32,
+    game: &'a Game,
+}
+
+fn format_error(e: impl std::fmt::Display) -> Error {
+    Error::Format(e.to_string())
+}
+
+fn migrate_v0(game: Value) -> Result<Value> {
+    Ok(serde_json::json!({ "version": 1, "game": game }))
+}
+
+/// 各ラウンドに、誰も宣言していない競りを加える。パスしたかどうかは保存された人数分持つ。
+fn migrate_v1(mut value: Value) -> Result<Value> {
+    let rounds = value
+        .pointer_mut("/game/rounds")
+        .and_then(Value::as_array_mut)
+        .ok_or(format_error("game.rounds is missing"))?;
+    for round in rounds.iter_mut() {
+        let players: Vec<Value> = round
+            .get("field_players")
+            .and_then(Value::as_array)
+            .ok_or(format_error("field_players is missing"))?
+            .iter()
+            .map(|p| p["player"].clone())
+            .collect();
+        let passed = vec![false; players.len()];
+        round["bidding"] = serde_json::json!({
+            "players": players,
+            "turn": 0,
+            "passed": passed,
+            "bids": [],
+            "highest": null,
+        });
+    }
+    value["version"] = 2.into();
+    Ok(value)
+}
+
+/// 各ラウンドに空のイベントの列を加える。それまでの経過は残っていないので組み立て直せない。
+fn migrate_v2(mut value: Value) -> Result<Value> {
+    let rounds = value
+        .pointer_mut("/game/rounds")
+        .and_then(Value::as_array_mut)
+        .ok_or(format_error("game.rounds is missing"))?;
+    for round in rounds.iter_mut() {
+        round["events"] = serde_json::json!([]);
+    }
+    value["version"] = 3.into();
+    Ok(value)
+}
+
+/// 各ラウンドを、取り消した操作がなく、いつでも戻せる状態にする。
+fn migrate_v3(mut value: Value) -> Result<Value> {
+    let rounds = value
+        .pointer_mut("/game/rounds")
+        .and_then(Value::as_array_mut)
+        .ok_or(format_error("game.rounds is missing"))?;
+    for round in rounds.iter_mut() {
+        round["redo"] = serde_json::json!([]);
+        round["undo_policy"] = serde_json::json!("Allowed");
+    }
+    value["version"] = 4.into();
+    Ok(value)
+}
+
+/// ゲーム、各ラウンドと配り方のイベントを、それまでと同じ既定のルールにする。
+fn migrate_v4(mut value: Value) -> Result<Value> {
+    let rules = serde_json::to_value(RuleSet::default()).map_err(format_error)?;
+    let game = value
+        .get_mut("game")
+        .and_then(Value::as_object_mut)
+        .ok_or(format_error("game is missing"))?;
+    game.insert("rules".to_string(), rules.clone());
+    let rounds = game
+        .get_mut("rounds")
+        .and_then(Value::as_array_mut)
+        .ok_or(format_error("game.rounds is missing"))?;
+    for round in rounds.iter_mut() {
+        round["rules"] = rules.clone();
+        let events = round
+            .get_mut("events")
+            .and_then(Value::as_array_mut)
+            .ok_or(format_error("events is missing"))?;
+        for dealt in events.iter_mut().filter_map(|e| e.get_mut("Dealt")) {
+            dealt["rules"] = rules.clone();
+        }
+    }
+    value["version"] = 5.into();
+    Ok(value)
+}
+
+/// 各ラウンドの競りに、そのラウンドのルールを持たせる。
+fn migrate_v5(mut value: Value) -> Result<Value> {
+    let rounds = value
+        .pointer_mut("/game/rounds")
+        .and_then(Value::as_array_mut)
+        .ok_or(format_error("game.rounds is missing"))?;
+    for round in rounds.iter_mut() {
+        let rules = round
+            .get("rules")
+            .cloned()
+            .ok_or(format_error("rules is missing"))?;
+        round["bidding"]["rules"] = rules;
+    }
+    value["version"] = 6.into();
+    Ok(value)
+}
+
+fn version(value: &Value) -> Result<u32> {
+    match value.get("version") {
+        None => Ok(0),
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or(format_error("version must be an unsigned integer")),
+    }
+}
+
+/// 保存データを今のバージョンまで移行して`Game`に戻す。
+pub fn from_value(mut value: Value) -> Result<Game> {
+    let mut v = version(&value)?;
+    if v > VERSION {
+        return Err(Error::UnsupportedVersion(v));
+    }
+    while v < VERSION {
+        value = MIGRATIONS[v as usize](value)?;
+        v = version(&value)?;
+    }
+    let game = value
+        .get_mut("game")
+        .map(Value::take)
+        .ok_or(format_error("game is missing"))?;
+    serde_json::from_value(game).map_err(format_error)
+}
+
+pub fn to_json(game: &Game) -> Result<String> {
+    serde_json::to_string(&Envelope {
+        version: VERSION,
+        game,
+    })
+    .map_err(format_error)
+}
+
+pub fn from_json(json: &str) -> Result<Game> {
+    from_value(serde_json::from_str(json).map_err(format_error)?)
+}
+
+pub fn to_vec(game: &Game) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(
+        &Envelope {
+            version: VERSION,
+            game,
+        },
+        &mut bytes,
+    )
+    .map_err(format_error)?;
+    Ok(bytes)
+}
+
+pub fn from_slice(bytes: &[u8]) -> Result<Game> {
+    from_value(ciborium::from_reader(bytes).map_err(format_error)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::Players;
+
+    fn game() -> Game {
+        let mut game = Game::new(Players::default()).unwrap();
+        game.new_round_with_seed(1).unwrap();
+        game
+    }
+
+    #[test]
+    fn test_json() -> anyhow::Result<()> {
+        let game = game();
+        let json = to_json(&game)?;
+        let value: Value = serde_json::from_str(&json)?;
+        assert_eq!(value["version"], VERSION);
+        assert_eq!(to_json(&from_json(&json)?)?, json);
+        Ok(())
+    }
+
+    #[test]
+    fn test_binary() -> anyhow::Result<()> {
+        let game = game();
+        let bytes = to_vec(&game)?;
+        assert!(bytes.len() < to_json(&game)?.len());
+        assert_eq!(to_vec(&from_slice(&bytes)?)?, bytes);
+        Ok(())
+    }
+
+    /// `version`から移行したときに期待する保存データ。version 3より前ならイベントの列は空になる。
+    fn migrated(game: &Game, version: u32) -> anyhow::Result<Value> {
+        let mut value: Value = serde_json::from_str(&to_json(game)?)?;
+        for round in value["game"]["rounds"].as_array_mut().unwrap() {
+            if version < 3 {
+                round["events"] = serde_json::json!([]);
+            }
+        }
+        Ok(value)
+    }
+
+    /// `version`の保存データを作る。
+    fn old(game: &Game, version: u32) -> anyhow::Result<Value> {
+        let mut value = serde_json::json!({ "version": version, "game": game });
+        for round in value["game"]["rounds"].as_array_mut().unwrap() {
+            round["bidding"].as_object_mut().unwrap().remove("rules");
+        }
+        if version == 5 {
+            return Ok(value);
+        }
+        value["game"].as_object_mut().unwrap().remove("rules");
+        for round in value["game"]["rounds"].as_array_mut().unwrap() {
+            let round = round.as_object_mut().unwrap();
+            round.remove("rules");
+            for event in round["events"].as_array_mut().unwrap() {
+                if let Some(dealt) = event.get_mut("Dealt") {
+                    dealt.as_object_mut().unwrap().remove("rules");
+                }
+            }
+            if version < 4 {
+                round.remove("redo");
+                round.remove("undo_policy");
+            }
+            if version < 3 {
+                round.remove("events");
+            }
+            if version < 2 {
+                round.remove("bidding");
+            }
+        }
+        Ok(value)
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(1)]
+    #[case(2)]
+    #[case(3)]
+    #[case(4)]
+    #[case(5)]
+    fn test_migrate(#[case] version: u32) -> anyhow::Result<()> {
+        let game = game();
+        let json = to_json(&from_value(old(&game, version)?)?)?;
+        assert_eq!(
+            serde_json::from_str::<Value>(&json)?,
+            migrated(&game, version)?
+        );
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(4)]
+    #[case(6)]
+    fn test_migrate_v1_players(#[case] n: usize) -> anyhow::Result<()> {
+        let rules = RuleSet::for_players(n);
+        let mut game = Game::with_rules(Players::with_len(n), rules)?;
+        game.new_round_with_seed(1)?;
+        let v1 = old(&game, 1)?;
+        let v2 = migrate_v1(v1)?;
+        let passed = v2["game"]["rounds"][0]["bidding"]["passed"]
+            .as_array()
+            .unwrap();
+        assert_eq!(passed.len(), n);
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_migrated() -> anyhow::Result<()> {
+        // version 3より前のラウンドは配ったときのイベントを持たないので戻せない
+        let game = from_value(old(&game(), 2)?)?;
+        let mut round: crate::round::Round =
+            serde_json::from_value(serde_json::to_value(&game.rounds()[0])?)?;
+        let player = round.next_actor().unwrap();
+        round.pass(&player)?;
+        assert_eq!(round.undo(), Err(Error::NothingToUndo));
+        assert_eq!(round.undo_trick(), Err(Error::NothingToUndo));
+        assert_eq!(round.bidding().bids().len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_v0() -> anyhow::Result<()> {
+        let game = game();
+        let v0 = old(&game, 0)?["game"].to_string();
+        let json = to_json(&from_json(&v0)?)?;
+        assert_eq!(serde_json::from_str::<Value>(&json)?, migrated(&game, 0)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let json = serde_json::json!({ "version": VERSION + 1, "game": {} }).to_string();
+        assert_eq!(
+            from_json(&json).err(),
+            Some(Error::UnsupportedVersion(VERSION + 1))
+        );
+        assert!(matches!(from_json("[]"), Err(Error::Format(_))));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::error::{Error, Result};
+use crate::player::{Player, Role};
+use crate::round::{Phase, Round, Team};
+
+/// 終了したラウンドの精算。
+///
+/// 1単位の点数は、ナポレオン軍が勝てば`Declaration::base_score`に立ちを超えて取った枚数を足したもの
+/// (20枚立ちで勝てば`base_score`の2倍)、負ければ立ちに足りなかった枚数。
+/// ただし20枚立ち以外で20枚全て取ったときは`base_score`で負けになる。
+/// 副官は1単位、連合軍はそれぞれ1単位を受け渡し、ナポレオンは残りを受け渡す。
+/// 5人ならナポレオンは2単位、一人立ちのときは4単位になる。
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Settlement {
+    pub winner: Team,
+    pub napoleon_score: usize,
+    pub unit: i32,
+    pub deltas: Vec<(Player, i32)>,
+}
+
+impl Settlement {
+    pub fn new(round: &Round) -> Result<Self> {
+        if round.phase() != Phase::Finished {
+            return Err(Error::NotFinished);
+        }
+        let (napoleon_score, _) = round.team_score()?;
+        Self::with_napoleon_score(round, napoleon_score)
+    }
+
+    /// ナポレオン軍が`napoleon_score`枚取って`round`が終わったときの精算。
+    /// `Round::play`が最後のトリックを進める前に計算するので、`round`の段階は見ない。
+    pub(crate) fn with_napoleon_score(round: &Round, napoleon_score: usize) -> Result<Self> {
+        let declaration = round.declaration().ok_or(Error::DeclarationNotSet)?;
+        let (winner, unit) = outcome(
+            declaration.number,
+            declaration.base_score(round.rules()),
+            napoleon_score,
+        );
+        let sign = match winner {
+            Team::Napoleon => 1,
+            Team::Union => -1,
+        };
+        let unions = round
+            .field_players
+            .0
+            .iter()
+            .filter(|p| p.role == Role::Union)
+            .count() as i32;
+        let napoleon_share = if round.is_alone() { unions } else { unions - 1 };
+
+        let deltas = round
+            .field_players
+            .0
+            .iter()
+            .map(|p| {
+                let share = match p.role {
+                    Role::Napoleon => napoleon_share,
+                    Role::Aide => 1,
+                    Role::Union => -1,
+                };
+                (p.player.clone(), sign * share * unit)
+            })
+            .collect();
+        Ok(Settlement {
+            winner,
+            napoleon_score,
+            unit,
+            deltas,
+        })
+    }
+
+    pub fn delta(&self, player: &Player) -> Option<i32> {
+        self.deltas
+            .iter()
+            .find(|(p, _)| p == player)
+            .map(|(_, d)| *d)
+    }
+}
+
+/// 立ちの枚数と`Declaration::base_score`、ナポレオン軍が取った絵札の枚数から、勝ったチームと1単位の点数を決める。
+pub(crate) fn outcome(number: usize, base_score: usize, napoleon_score: usize) -> (Team, i32) {
+    let base = base_score as i32;
+    if number == 20 {
+        return if napoleon_score == 20 {
+            (Team::Napoleon, base * 2)
+        } else {
+            (Team::Union, (number - napoleon_score) as i32)
+        };
+    }
+    if napoleon_score == 20 {
+        return (Team::Union, base);
+    }
+    if napoleon_score >= number {
+        // 立ちを超えて取った枚数も足す
+        return (Team::Napoleon, base + (napoleon_score - number) as i32);
+    }
+    (Team::Union, (number - napoleon_score) as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::Card;
+    use crate::declaration::Declaration;
+    use crate::player::Players;
+    use crate::rule::RuleSet;
+    use crate::trick::Play;
+
+    #[rstest::rstest]
+    #[test]
+    #[case(13, 1, 13, Team::Napoleon, 1)]
+    // 立ちを超えた3枚を足す
+    #[case(15, 3, 18, Team::Napoleon, 6)]
+    #[case(20, 8, 20, Team::Napoleon, 16)]
+    #[case(15, 3, 12, Team::Union, 3)]
+    #[case(20, 8, 19, Team::Union, 1)]
+    // 20枚立ち以外で全て取ると負け
+    #[case(16, 4, 20, Team::Union, 4)]
+    // 4人なら15枚立ちで2単位
+    #[case(15, 2, 16, Team::Napoleon, 3)]
+    fn test_outcome(
+        #[case] number: usize,
+        #[case] base_score: usize,
+        #[case] napoleon_score: usize,
+        #[case] winner: Team,
+        #[case] unit: i32,
+    ) {
+        assert_eq!(outcome(number, base_score, napoleon_score), (winner, unit));
+    }
+
+    fn finished_round(aide: impl Fn(&Round) -> Card) -> anyhow::Result<Round> {
+        finished_round_with_rules(RuleSet::default(), aide)
+    }
+
+    fn finished_round_with_rules(
+        rules: RuleSet,
+        aide: impl Fn(&Round) -> Card,
+    ) -> anyhow::Result<Round> {
+        let players = Players::with_len(rules.players);
+        let mut r = Round::new_with_rules(players.clone(), 1, rules)?;
+        let d =
+            Declaration::with_rules(players.0[0].clone(), None, rules.min_bid, aide(&r), &rules)?;
+        r.set_declaration(d)?;
+        r.exchange(&players.0[0], r.opens.clone())?;
+        while let Some(player) = r.next_player() {
+            let card = r.legal_plays()[0];
+            r.play(Play::new(player, card))?;
+        }
+        Ok(r)
+    }
+
+    #[test]
+    fn test_settlement_not_finished() -> anyhow::Result<()> {
+        let r = Round::new(Players::default())?;
+        assert_eq!(Settlement::new(&r), Err(Error::NotFinished));
+        Ok(())
+    }
+
+    #[test]
+    fn test_settlement() -> anyhow::Result<()> {
+        let players = Players::default();
+        let r = finished_round(|r| r.field_players.0[1].hands.to_vec()[0])?;
+        let s = Settlement::new(&r)?;
+        let sign = match s.winner {
+            Team::Napoleon => 1,
+            Team::Union => -1,
+        };
+        assert_eq!(s.deltas.iter().map(|(_, d)| d).sum::<i32>(), 0);
+        assert_eq!(s.delta(&players.0[0]), Some(sign * 2 * s.unit));
+        assert_eq!(s.delta(&players.0[1]), Some(sign * s.unit));
+        assert_eq!(s.delta(&players.0[2]), Some(-sign * s.unit));
+        Ok(())
+    }
+
+    #[test]
+    fn test_settlement_isolated() -> anyhow::Result<()> {
+        let players = Players::default();
+        // 副官のカードがオープンにあると一人立ち
+        let r = finished_round(|r| r.opens[0])?;
+        let s = Settlement::new(&r)?;
+        let sign = match s.winner {
+            Team::Napoleon => 1,
+            Team::Union => -1,
+        };
+        assert_eq!(s.deltas.iter().map(|(_, d)| d).sum::<i32>(), 0);
+        assert_eq!(s.delta(&players.0[0]), Some(sign * 4 * s.unit));
+        assert_eq!(s.delta(&players.0[1]), Some(-sign * s.unit));
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(4, 1, 3)]
+    #[case(6, 3, 5)]
+    fn test_settlement_players(
+        #[case] n: usize,
+        #[case] napoleon_share: i32,
+        #[case] isolated_share: i32,
+    ) -> anyhow::Result<()> {
+        let rules = RuleSet::for_players(n);
+        let players = Players::with_len(n);
+        for (aide, share) in [(1, napoleon_share), (0, isolated_share)] {
+            // 副官のカードがオープンにあると一人立ち
+            let r = finished_round_with_rules(rules, |r| match aide {
+                1 => r.field_players.0[1].hands.to_vec()[0],
+                _ => r.opens[0],
+            })?;
+            let s = Settlement::new(&r)?;
+            let sign = match s.winner {
+                Team::Napoleon => 1,
+                Team::Union => -1,
+            };
+            assert_eq!(s.deltas.len(), n);
+            assert_eq!(s.deltas.iter().map(|(_, d)| d).sum::<i32>(), 0);
+            assert_eq!(s.delta(&players.0[0]), Some(sign * share * s.unit));
+            assert_eq!(s.delta(&players.0[n - 1]), Some(-sign * s.unit));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+//! 全員の手札を見てプレイの段階を最後まで読み切る(ダブルダミー)。
+//!
+//! ナポレオン軍は取る絵札を多く、連合軍は少なくしようとする。
+//! 値はナポレオン軍が最後までに取る絵札の枚数で、既に取った分も含む。
+use std::collections::HashMap;
+
+use crate::card::{Card, Suit};
+use crate::card_set::CardSet;
+use crate::error::Result;
+use crate::playout::PlayState;
+use crate::round::Round;
+use crate::rule::RuleSet;
+
+/// 読んだ結果を覚えておくので、続けて同じラウンドを読むときは速くなる。
+#[derive(Debug, Default)]
+pub struct Solver {
+    /// トリックの切れ目で読んだ結果の上限と下限。残りのカードと最初に出す席で決まる。
+    table: HashMap<(u64, usize), (usize, usize)>,
+    /// `table`を作ったときの切り札、ナポレオンと副官の席、ルール。違えば`table`を捨てる
+    context: Option<(Option<Suit>, usize, Option<usize>, RuleSet)>,
+    /// 調べた局面の数
+    pub nodes: usize,
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 両軍が最善を尽くしたときにナポレオン軍が取る絵札の枚数。
+    pub fn solve(&mut self, round: &Round) -> Result<usize> {
+        let state = PlayState::from_round(round)?;
+        self.prepare(&state);
+        Ok(state.napoleon_points + self.search(&state, 0, 20))
+    }
+
+    /// 次の席が出せるカードそれぞれについて、出した後に両軍が最善を尽くしたときの`solve`の値。
+    pub fn play_values(&mut self, round: &Round) -> Result<Vec<(Card, usize)>> {
+        let state = PlayState::from_round(round)?;
+        self.prepare(&state);
+        Ok(state
+            .legal()
+            .into_iter()
+            .map(|card| {
+                let mut next = state.clone();
+                next.play(card);
+                (card, next.napoleon_points + self.search(&next, 0, 20))
+            })
+            .collect())
+    }
+
+    /// 前に読んだ局面と両軍の分かれ方やルールが違えば、覚えた結果を捨てる。
+    /// 覚えるのはさらに取る枚数なので、既に取った枚数は違ってもよい。
+    fn prepare(&mut self, state: &PlayState) {
+        let context = Some((state.trump, state.napoleon, state.aide, state.rules));
+        if self.context != context {
+            self.table.clear();
+            self.context = context;
+        }
+    }
+
+    /// `state`からナポレオン軍がさらに取る絵札の枚数。`alpha`以下や`beta`以上ならその境界までしか正確でない。
+    fn search(&mut self, state: &PlayState, mut alpha: usize, mut beta: usize) -> usize {
+        if state.is_finished() {
+            return 0;
+        }
+        self.nodes += 1;
+        let remaining = (in_hands(state) | state.trick.iter().collect())
+            .faces()
+            .len();
+        beta = beta.min(remaining);
+        if alpha >= beta {
+            return beta;
+        }
+
+        let key = state
+            .trick
+            .is_empty()
+            .then(|| (in_hands(state).bits(), state.leader));
+        if let Some((lower, upper)) = key.and_then(|k| self.table.get(&k)) {
+            if lower == upper || *lower >= beta {
+                return *lower;
+            }
+            if *upper <= alpha {
+                return *upper;
+            }
+            alpha = alpha.max(*lower);
+            beta = beta.min(*upper);
+        }
+
+        let maximize = state.is_napoleon_side(state.to_move());
+        let mut legal = state.legal().to_vec();
+        legal.sort_by_key(|c| std::cmp::Reverse(c.rank()));
+        let mut best = if maximize { 0 } else { remaining };
+        let (mut a, mut b) = (alpha, beta);
+        for card in legal {
+            let mut next = state.clone();
+            next.play(card);
+            let gained = next.napoleon_points - state.napoleon_points;
+            let value =
+                gained + self.search(&next, a.saturating_sub(gained), b.saturating_sub(gained));
+            if maximize {
+                best = best.max(value);
+                a = a.max(best);
+            } else {
+                best = best.min(value);
+                b = b.min(best);
+            }
+            if a >= b {
+                break;
+            }
+        }
+
+        if let Some(k) = key {
+            let (lower, upper) = self.table.get(&k).copied().unwrap_or((0, remaining));
+            let bound = if best <= alpha {
+                (lower, best)
+            } else if best >= beta {
+                (best, upper)
+            } else {
+                (best, best)
+            };
+            self.table.insert(k, bound);
+        }
+        best
+    }
+}
+
+/// まだ誰かの手札にあるカード。
+fn in_hands(state: &PlayState) -> CardSet {
+    state
+        .hands
+        .iter()
+        .fold(CardSet::new(), |cards, h| cards | *h)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::Suit;
+    use crate::declaration::Declaration;
+    use crate::error::Error;
+    use crate::player::Players;
+    use crate::trick::Play;
+
+    /// 枝刈りも表も使わずに読み切る。
+    fn minimax(state: &PlayState) -> usize {
+        if state.is_finished() {
+            return state.napoleon_points;
+        }
+        let values = state.legal().iter().map(|card| {
+            let mut next = state.clone();
+            next.play(card);
+            minimax(&next)
+        });
+        if state.is_napoleon_side(state.to_move()) {
+            values.max().unwrap()
+        } else {
+            values.min().unwrap()
+        }
+    }
+
+    fn played_round(seed: u64, n: usize) -> anyhow::Result<Round> {
+        played_round_by(seed, n, 0)
+    }
+
+    /// `napoleon`の席がナポレオンになって、オープンをそのまま捨てる。
+    fn played_round_by(seed: u64, n: usize, napoleon: usize) -> anyhow::Result<Round> {
+        let players = Players::default();
+        let mut round = Round::new_with_seed(players.clone(), seed)?;
+        let aide = round.field_players.0[3].hands.to_vec()[5];
+        round.set_declaration(Declaration::new(
+            players.0[napoleon].clone(),
+            Some(Suit::Heart),
+            13,
+            aide,
+        )?)?;
+        round.exchange(&players.0[napoleon], round.opens.clone())?;
+        for _ in 0..n {
+            round.play(Play::new(
+                round.next_player().unwrap(),
+                round.legal_plays()[0],
+            ))?;
+        }
+        Ok(round)
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(1, 35)]
+    #[case(2, 33)]
+    #[case(3, 37)]
+    #[case(4, 40)]
+    fn test_solve_same_as_minimax(#[case] seed: u64, #[case] n: usize) -> anyhow::Result<()> {
+        let round = played_round(seed, n)?;
+        let mut solver = Solver::new();
+        assert_eq!(
+            solver.solve(&round)?,
+            minimax(&PlayState::from_round(&round)?)
+        );
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(1, 35)]
+    #[case(3, 37)]
+    fn test_solve_different_rounds(#[case] seed: u64, #[case] n: usize) -> anyhow::Result<()> {
+        // 残りのカードは同じで、ナポレオンの席だけが違う
+        let first = played_round_by(seed, n, 0)?;
+        let second = played_round_by(seed, n, 1)?;
+        let mut solver = Solver::new();
+        for round in [&first, &second, &first] {
+            assert_eq!(
+                solver.solve(round)?,
+                minimax(&PlayState::from_round(round)?)
+            );
+        }
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case(1, 25)]
+    #[case(2, 28)]
+    fn test_play_values(#[case] seed: u64, #[case] n: usize) -> anyhow::Result<()> {
+        let mut round = played_round(seed, n)?;
+        let mut solver = Solver::new();
+        let value = solver.solve(&round)?;
+        let values = solver.play_values(&round)?;
+        let player = round.next_player().unwrap();
+        let napoleon_side = PlayState::from_round(&round)?.is_napoleon_side(
+            round
+                .field_players
+                .0
+                .iter()
+                .position(|p| p.player == player)
+                .unwrap(),
+        );
+        let best = values.iter().map(|(_, v)| *v);
+        let best = if napoleon_side {
+            best.max()
+        } else {
+            best.min()
+        };
+        assert_eq!(Some(value), best);
+
+        // 最善のカードを出しても値は変わらない
+        let (card, _) = values.iter().find(|(_, v)| *v == value).unwrap();
+        round.play(Play::new(player, *card))?;
+        assert_eq!(Solver::new().solve(&round)?, value);
+        Ok(())
+    }
+
+    #[test]
+    fn test_last_play() -> anyhow::Result<()> {
+        let round = played_round(1, 49)?;
+        let napoleon_points = PlayState::from_round(&round)?.napoleon_points;
+        let values = Solver::new().play_values(&round)?;
+        assert_eq!(values.len(), 1);
+        assert!(values[0].1 >= napoleon_points);
+        Ok(())
+    }
+
+    #[test]
+    fn test_solve_not_playing() -> anyhow::Result<()> {
+        let round = Round::new_with_seed(Players::default(), 1)?;
+        assert!(matches!(
+            Solver::new().solve(&round),
+            Err(Error::PhaseViolation { .. })
+        ));
+        Ok(())
+    }
+}
@@ -1,52 +1,126 @@
-use crate::card::Card;
-use crate::player::Player;
+use crate::card::{lead_suit, Card, Suit};
+use crate::card_set::CardSet;
+use crate::error::{Error, Result};
+use crate::player::{FieldPlayer, Player};
+use crate::rule::RuleSet;
 
-#[allow(dead_code)]
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct Play {
     pub player: Player,
     pub card: Card,
 }
 
 impl Play {
-    #[allow(dead_code)]
     pub fn new(player: Player, card: Card) -> Self {
         Play { player, card }
     }
 }
 
-pub type TrickArray = [Play; 5];
+/// 全員が出し終えたトリック。席の数だけ並ぶ。
+pub type TrickArray = Vec<Play>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Trick {
     pub plays: Vec<Play>,
+    /// 出す人数
+    #[serde(default = "default_size")]
+    size: usize,
 }
 
-impl Default for Trick {
-    fn default() -> Self {
-        Self::new()
-    }
+/// 人数を持たない古い保存データは5人で遊んでいた。
+fn default_size() -> usize {
+    5
 }
 
 impl Trick {
-    #[allow(dead_code)]
-    pub fn new() -> Self {
-        Trick { plays: Vec::new() }
+    /// `rules`の人数が出すトリック。
+    pub fn new(rules: &RuleSet) -> Self {
+        Self::with_size(rules.players)
+    }
+
+    /// `size`人が出すトリック。
+    pub fn with_size(size: usize) -> Self {
+        Trick {
+            plays: Vec::new(),
+            size,
+        }
     }
 
-    #[allow(dead_code)]
-    pub fn add(&mut self, play: Play) {
+    pub fn add(&mut self, play: Play) -> Result<()> {
+        if self.is_finished() {
+            return Err(Error::TrickFinished);
+        }
+        if self.plays.iter().any(|p| p.player == play.player) {
+            return Err(Error::AlreadyPlayed(play.player));
+        }
         self.plays.push(play);
+        Ok(())
+    }
+
+    /// 台札のスート。ジョーカーだけが出ていれば`None`。
+    pub fn lead_suit(&self) -> Option<Suit> {
+        lead_suit(self.plays.iter().map(|p| &p.card))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.plays.len() == self.size
     }
 
-    #[allow(dead_code)]
     pub fn last_player(&self) -> Option<Player> {
         Some(self.plays.last()?.player.clone())
     }
 
-    pub(crate) fn array(&self) -> anyhow::Result<TrickArray> {
-        anyhow::ensure!(self.plays.len() == 5, "This Trick is not finished yet");
-        Ok(self.plays.clone().try_into().unwrap())
+    pub(crate) fn array(&self) -> Result<TrickArray> {
+        if !self.is_finished() {
+            return Err(Error::TrickNotFinished);
+        }
+        Ok(self.plays.clone())
+    }
+}
+
+/// `player`が`trick`に出せるカード。台札と同じスートを持っていれば、それを出さなければならない。
+/// マイティ、正ジャック、裏ジャック、ジョーカーはマストフォローの例外としていつでも出せる。
+pub fn legal_plays(
+    player: &FieldPlayer,
+    trick: &Trick,
+    trump: Option<Suit>,
+    rules: &RuleSet,
+) -> Vec<Card> {
+    legal_cards(player.hands, trick.lead_suit(), trump, rules).to_vec()
+}
+
+/// 台札が`lead`のときに`hands`から出せるカード。
+pub(crate) fn legal_cards(
+    hands: CardSet,
+    lead: Option<Suit>,
+    trump: Option<Suit>,
+    rules: &RuleSet,
+) -> CardSet {
+    match lead {
+        Some(lead) if !hands.of_suit(lead).is_empty() => {
+            hands & (CardSet::suit(lead) | rules.free_cards(trump))
+        }
+        _ => hands,
+    }
+}
+
+/// `play`が`player`の手札から`trick`に出せるかを検証する。
+pub fn validate_play(
+    player: &FieldPlayer,
+    trick: &Trick,
+    trump: Option<Suit>,
+    rules: &RuleSet,
+    card: &Card,
+) -> Result<()> {
+    if !player.has(card) {
+        return Err(Error::NotInHands(*card));
+    }
+    // 台札のスートがなければどのカードも出せる
+    match trick.lead_suit() {
+        Some(lead) if !legal_plays(player, trick, trump, rules).contains(card) => {
+            Err(Error::IllegalPlay { card: *card, lead })
+        }
+        _ => Ok(()),
     }
 }
 
@@ -57,29 +131,45 @@ mod tests {
 
     #[test]
     fn test_trick_add() -> anyhow::Result<()> {
-        let mut trick = Trick::new();
+        let mut trick = Trick::new(&RuleSet::default());
 
         let players = crate::player::Players::default();
-        let r = Round::new(players.clone());
+        let r = Round::new(players.clone())?;
         trick.add(Play::new(
             r.field_players.0[0].player.clone(),
-            r.field_players.0[0].hands[0],
-        ));
+            r.field_players.0[0].hands.to_vec()[0],
+        ))?;
 
         Ok(())
     }
 
+    #[rstest::rstest]
+    #[test]
+    #[case(4)]
+    #[case(5)]
+    #[case(6)]
+    fn test_trick_new_players(#[case] n: usize) -> anyhow::Result<()> {
+        let mut trick = Trick::new(&RuleSet::for_players(n));
+        let players = crate::player::Players::with_len(n);
+        for (p, id) in players.0.iter().zip(1..) {
+            assert!(!trick.is_finished());
+            trick.add(Play::new(p.clone(), Card::try_from(id)?))?;
+        }
+        assert!(trick.is_finished());
+        Ok(())
+    }
+
     #[test]
     fn test_trick_last_player() -> anyhow::Result<()> {
-        let mut trick = Trick::new();
+        let mut trick = Trick::new(&RuleSet::default());
         assert_eq!(trick.last_player(), None);
 
         let players = crate::player::Players::default();
-        let r = Round::new(players.clone());
+        let r = Round::new(players.clone())?;
         trick.add(Play::new(
             r.field_players.0[0].player.clone(),
-            r.field_players.0[0].hands[0],
-        ));
+            r.field_players.0[0].hands.to_vec()[0],
+        ))?;
         assert_eq!(
             trick.last_player(),
             Some(r.field_players.0[0].player.clone())
@@ -87,8 +177,8 @@ mod tests {
 
         trick.add(Play::new(
             r.field_players.0[1].player.clone(),
-            r.field_players.0[1].hands[0],
-        ));
+            r.field_players.0[1].hands.to_vec()[0],
+        ))?;
         assert_eq!(
             trick.last_player(),
             Some(r.field_players.0[1].player.clone())
@@ -98,19 +188,122 @@ mod tests {
 
     #[test]
     fn test_trick_array() -> anyhow::Result<()> {
-        let mut trick = Trick::new();
+        let mut trick = Trick::new(&RuleSet::default());
         assert!(trick.array().is_err());
 
         let players = crate::player::Players::default();
-        let r = Round::new(players.clone());
+        let r = Round::new(players.clone())?;
         for p in r.field_players.0.iter() {
-            trick.add(Play::new(p.player.clone(), p.hands[0]));
+            trick.add(Play::new(p.player.clone(), p.hands.to_vec()[0]))?;
         }
 
         for (t, p) in std::iter::zip(trick.array()?, r.field_players.0.iter()) {
             assert_eq!(t.player, p.player);
-            assert_eq!(t.card, p.hands[0]);
+            assert_eq!(t.card, p.hands.to_vec()[0]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_trick_add_invalid() -> anyhow::Result<()> {
+        let players = crate::player::Players::default();
+        let mut trick = Trick::new(&RuleSet::default());
+        trick.add(Play::new(players.0[0].clone(), Card::try_from(1)?))?;
+        assert_eq!(
+            trick.add(Play::new(players.0[0].clone(), Card::try_from(2)?)),
+            Err(Error::AlreadyPlayed(players.0[0].clone()))
+        );
+        for (i, p) in players.0.iter().enumerate().skip(1) {
+            trick.add(Play::new(p.clone(), Card::try_from(i as u8 + 1)?))?;
         }
+        assert!(trick.is_finished());
+        assert_eq!(
+            trick.add(Play::new(players.0[0].clone(), Card::try_from(10)?)),
+            Err(Error::TrickFinished)
+        );
+        Ok(())
+    }
+
+    fn field_player(ids: &[u8]) -> FieldPlayer {
+        FieldPlayer::new(
+            crate::player::Player::default(),
+            ids.iter().map(|i| Card::try_from(*i).unwrap()).collect(),
+        )
+    }
+
+    fn led_trick(id: u8) -> Trick {
+        let mut trick = Trick::new(&RuleSet::default());
+        trick
+            .add(Play::new(
+                Player {
+                    id: "z".to_string(),
+                },
+                Card::try_from(id).unwrap(),
+            ))
+            .unwrap();
+        trick
+    }
+
+    #[rstest::rstest]
+    #[test]
+    // 最初に出すときは何でも出せる
+    #[case(None, None, &[2, 15, 28], &[2, 15, 28])]
+    // 台札のスートを持っていればそれしか出せない
+    #[case(Some(3), None, &[2, 4, 15, 28], &[2, 4])]
+    // 台札のスートを持っていなければ何でも出せる
+    #[case(Some(3), None, &[15, 28], &[15, 28])]
+    // マイティはいつでも出せる
+    #[case(Some(16), None, &[1, 17, 28], &[1, 17])]
+    // 正ジャックと裏ジャックはいつでも出せる
+    #[case(Some(16), Some(Suit::Club), &[11, 17, 28, 50], &[11, 17, 50])]
+    #[case(Some(16), Some(Suit::Diamond), &[11, 17, 28, 50], &[17])]
+    // ジョーカーから出したときは次のカードまで何でも出せる
+    #[case(Some(53), None, &[2, 15, 28], &[2, 15, 28])]
+    fn test_legal_plays(
+        #[case] lead: Option<u8>,
+        #[case] trump: Option<Suit>,
+        #[case] hands: &[u8],
+        #[case] legal: &[u8],
+    ) {
+        let trick = lead
+            .map(led_trick)
+            .unwrap_or_else(|| Trick::new(&RuleSet::default()));
+        let player = field_player(hands);
+        let legal: Vec<Card> = legal.iter().map(|i| Card::try_from(*i).unwrap()).collect();
+        assert_eq!(
+            legal_plays(&player, &trick, trump, &RuleSet::default()),
+            legal
+        );
+    }
+
+    #[test]
+    fn test_legal_plays_without_reverse_jack() {
+        let rules = RuleSet::from(crate::rule::Preset::Simple);
+        let player = field_player(&[11, 17, 28, 50]);
+        let legal: Vec<Card> = [17, 50].map(|i| Card::try_from(i).unwrap()).to_vec();
+        assert_eq!(
+            legal_plays(&player, &led_trick(16), Some(Suit::Club), &rules),
+            legal
+        );
+    }
+
+    #[test]
+    fn test_validate_play() -> anyhow::Result<()> {
+        let trick = led_trick(3);
+        let player = field_player(&[2, 4, 15]);
+        let rules = RuleSet::default();
+        assert!(validate_play(&player, &trick, None, &rules, &Card::try_from(2)?).is_ok());
+        assert_eq!(
+            validate_play(&player, &trick, None, &rules, &Card::try_from(5)?),
+            Err(Error::NotInHands(Card::try_from(5)?))
+        );
+        assert_eq!(
+            validate_play(&player, &trick, None, &rules, &Card::try_from(15)?),
+            Err(Error::IllegalPlay {
+                card: Card::try_from(15)?,
+                lead: Suit::Spade,
+            })
+        );
         Ok(())
     }
 }
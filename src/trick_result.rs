@@ -1,115 +1,170 @@
-use crate::card::{Card, Suit};
+use crate::card::{lead_suit, Card, Suit};
+use crate::card_set::CardSet;
+use crate::error::Result;
 use crate::player::Player;
+use crate::rule::RuleSet;
 use crate::trick::{Trick, TrickArray};
 
-struct TrickResultBuilder {
-    trick: TrickArray,
-    face_cards: Vec<Card>,
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TrickResult {
+    pub trick: TrickArray,
+    pub winner: Player,
+    pub face_cards: Vec<Card>,
 }
 
-impl TrickResultBuilder {
-    fn new(trick: &Trick) -> anyhow::Result<Self> {
-        let face_cards: Vec<Card> = trick
-            .plays
-            .iter()
-            .filter(|p| p.card.is_face())
-            .map(|p| p.card)
-            .collect();
-        Ok(TrickResultBuilder {
-            trick: (*trick).array()?,
-            face_cards,
+impl TrickResult {
+    /// 勝敗は`judge`で決める。
+    pub fn new(trick: &Trick, suit: Option<Suit>, n_round: u8, rules: &RuleSet) -> Result<Self> {
+        let trick = trick.array()?;
+        let cards: TrickCards = trick.iter().map(|p| p.card).collect();
+        let (winner, faces) = judge(&cards, suit, n_round, rules);
+        Ok(TrickResult {
+            winner: trick[winner].player.clone(),
+            face_cards: cards
+                .iter()
+                .copied()
+                .filter(|c| faces.contains(c))
+                .collect(),
+            trick,
         })
     }
+}
 
-    fn build(&self, winner_id: usize) -> anyhow::Result<TrickResult> {
-        Ok(TrickResult {
-            trick: self.trick.clone(),
-            face_cards: self.face_cards.clone(),
-            winner: self.trick[winner_id].player.clone(),
-        })
+/// 1トリックに出るカードの最大枚数。6人で遊ぶときの枚数。
+pub const MAX_TRICK_CARDS: usize = 6;
+
+/// 出した順に並べた1トリックのカード。割り当てをしないように固定の長さの配列に入れる。
+#[derive(Debug, Clone, Copy)]
+pub struct TrickCards {
+    cards: [Card; MAX_TRICK_CARDS],
+    len: u8,
+}
+
+impl TrickCards {
+    pub const fn new() -> Self {
+        TrickCards {
+            cards: [Card::JOKER; MAX_TRICK_CARDS],
+            len: 0,
+        }
+    }
+
+    /// `card`を最後に加える。`MAX_TRICK_CARDS`枚を超えるとpanicする。
+    pub fn push(&mut self, card: Card) {
+        self.cards[usize::from(self.len)] = card;
+        self.len += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
     }
 }
 
-#[allow(dead_code)]
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
-pub struct TrickResult {
-    pub trick: TrickArray,
-    pub winner: Player,
-    pub face_cards: Vec<Card>,
+impl Default for TrickCards {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl TrickResult {
-    #[allow(dead_code)]
-    pub fn new(trick: &Trick, suit: Option<Suit>, n_round: u8) -> anyhow::Result<Self> {
-        let builder = TrickResultBuilder::new(trick)?;
+impl std::ops::Deref for TrickCards {
+    type Target = [Card];
 
-        // almighty
-        if let Some(id) = builder.trick.iter().position(|c| c.card.is_almighty()) {
-            return builder.build(
-                builder
-                    .trick
-                    .iter()
-                    .position(|c| c.card.is_yoromeki())
-                    .unwrap_or(id),
-            );
-        }
+    fn deref(&self) -> &[Card] {
+        &self.cards[..usize::from(self.len)]
+    }
+}
 
-        // jack
-        if let Some(s) = suit {
-            let id = builder
-                .trick
-                .iter()
-                .position(|c| (c.card.number == 11) && (c.card.suit == s));
-            if let Some(i) = id {
-                return builder.build(i);
-            }
+impl PartialEq for TrickCards {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
 
-            // reverse jack
-            let rev_suit = s.reverse();
-            let id = builder
-                .trick
-                .iter()
-                .position(|c| (c.card.number == 11) && (c.card.suit == rev_suit));
-            if let Some(i) = id {
-                return builder.build(i);
-            }
+impl Eq for TrickCards {}
+
+impl FromIterator<Card> for TrickCards {
+    fn from_iter<I: IntoIterator<Item = Card>>(iter: I) -> Self {
+        let mut cards = TrickCards::new();
+        for card in iter {
+            cards.push(card);
         }
+        cards
+    }
+}
 
-        let first_suit = builder.trick[0].card.suit;
+/// 全員が出したトリックで勝つカードの位置と、トリックの中の絵札。割り当てをしないので探索で使う。
+pub fn judge(
+    cards: &TrickCards,
+    suit: Option<Suit>,
+    n_round: u8,
+    rules: &RuleSet,
+) -> (usize, CardSet) {
+    let set: CardSet = cards.iter().collect();
+    (winner(cards, set, suit, n_round, rules), set.faces())
+}
 
-        // same2
-        if n_round > 1 && (builder.trick.iter().all(|c| c.card.suit == first_suit)) {
-            if let Some(id) = builder.trick.iter().position(|c| c.card.number == 2) {
-                return builder.build(id);
-            }
+/// 出した順に並べた`cards`の中で勝つカードの位置。全員が出す前なら、その時点で勝っているカード。
+///
+/// マイティ(よろめきがあればよろめき)、ジョーカー、正ジャック、裏ジャック、2トリック目以降で全て同じスートなら2、
+/// 切り札、台札の順に強い。`rules`で使わない役札はただのカードになる。
+/// ジョーカーは最初に出すとどのカードにも負ける(ジョーカー請求)。
+pub fn trick_winner(cards: &TrickCards, suit: Option<Suit>, n_round: u8, rules: &RuleSet) -> usize {
+    winner(cards, cards.iter().collect(), suit, n_round, rules)
+}
+
+fn winner(cards: &[Card], set: CardSet, suit: Option<Suit>, n_round: u8, rules: &RuleSet) -> usize {
+    let position = |card: Card| cards.iter().position(|c| *c == card);
+    let card = |number, suit| Card { number, suit };
+
+    if rules.specials(n_round) {
+        // almighty
+        if set.contains(&card(1, Suit::Spade)) {
+            let yoromeki = position(card(12, Suit::Heart)).filter(|_| rules.yoromeki);
+            return yoromeki.or_else(|| position(card(1, Suit::Spade))).unwrap();
         }
 
-        let mut winner_id = 0;
-        for i in 1..5 {
-            let c = &builder.trick[i].card;
-            if c.suit == first_suit {
-                if c.number == 1 {
-                    return builder.build(i);
-                }
-                if c.number > builder.trick[winner_id].card.number {
-                    winner_id = i;
+        // joker
+        if let Some(id) = position(Card::JOKER).filter(|id| *id > 0) {
+            return id;
+        }
+
+        // jack, reverse jack
+        if let Some(s) = suit {
+            let reverse = Some(card(11, s.reverse())).filter(|_| rules.reverse_jack);
+            for jack in std::iter::once(card(11, s)).chain(reverse) {
+                if let Some(id) = position(jack) {
+                    return id;
                 }
             }
         }
-        builder.build(winner_id)
     }
+
+    let first_suit = lead_suit(cards);
+    let led = first_suit.map_or(CardSet::EMPTY, |s| set.of_suit(s));
+
+    // same2
+    if let Some(s) = first_suit.filter(|_| rules.same2 && n_round > 1 && led == set) {
+        if let Some(id) = position(card(2, s)) {
+            return id;
+        }
+    }
+
+    // trump, first suit
+    let strongest = suit
+        .map(|s| set.of_suit(s))
+        .filter(|t| !t.is_empty())
+        .unwrap_or(led);
+    strongest.last().and_then(position).unwrap_or(0)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rule::Preset;
     use crate::trick::Play;
     use rand::seq::SliceRandom;
 
-    #[allow(dead_code)]
     type FieldCardIds = [u8; 5];
 
-    #[allow(dead_code)]
     fn get_trick(ids: &FieldCardIds) -> Trick {
         let players = crate::player::Players::default();
         let roles = [
@@ -132,21 +187,23 @@ mod tests {
             .into_iter()
             .enumerate()
             .map(|(pid, p)| {
-                let mut hands: Vec<Card> = (0..9)
+                let mut hands: CardSet = (0..9)
                     .map(|i| Card::try_from(all_cards[(pid * 9) + i]).unwrap())
                     .collect();
-                hands.push(Card::try_from(ids[pid]).unwrap());
+                hands.insert(Card::try_from(ids[pid]).unwrap());
                 crate::player::FieldPlayer {
                     player: p,
                     role: roles[pid].clone(),
-                    hands: hands.try_into().unwrap(),
+                    hands,
                 }
             })
             .collect::<Vec<crate::player::FieldPlayer>>()
             .into();
-        let mut trick = Trick::new();
-        for p in field_players.0 {
-            trick.add(Play::new(p.player.clone(), p.hands[9]));
+        let mut trick = Trick::new(&RuleSet::default());
+        for (p, id) in field_players.0.into_iter().zip(ids.iter()) {
+            trick
+                .add(Play::new(p.player.clone(), Card::try_from(*id).unwrap()))
+                .unwrap();
         }
         trick
     }
@@ -158,11 +215,125 @@ mod tests {
         Ok(())
     }
 
+    #[rstest::rstest]
+    #[test]
+    #[case("S3 S5", None, 2, 1)]
+    #[case("H3 SA", Some(Suit::Heart), 1, 1)]
+    #[case("SA D4 HQ", None, 1, 2)]
+    #[case("D3 CJ", Some(Suit::Spade), 1, 1)]
+    #[case("D3 D2", None, 2, 1)]
+    #[case("D3 D2", None, 1, 0)]
+    #[case("D3 C2 DK", Some(Suit::Club), 3, 1)]
+    fn test_trick_winner(
+        #[case] cards: &str,
+        #[case] trump: Option<Suit>,
+        #[case] n_round: u8,
+        #[case] expected: usize,
+    ) -> anyhow::Result<()> {
+        let cards: TrickCards = crate::card::parse_hands(cards)?.into_iter().collect();
+        assert_eq!(
+            trick_winner(&cards, trump, n_round, &RuleSet::default()),
+            expected
+        );
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case("S3 S5 S2 S10 SK", None, 2, 2, "S10 SK")]
+    #[case("S3 S5 S2 S10 SK", None, 1, 4, "S10 SK")]
+    #[case("H3 SA HQ D10 C2", Some(Suit::Heart), 1, 2, "SA HQ D10")]
+    #[case("D3 SJ CJ DA D2", Some(Suit::Club), 2, 2, "SJ CJ DA")]
+    #[case("D3 SJ HJ DA D2", Some(Suit::Club), 2, 1, "SJ HJ DA")]
+    #[case("D3 C2 DK H4 D5", Some(Suit::Club), 3, 1, "DK")]
+    #[case("D3 C2 DK H4 D5", None, 3, 2, "DK")]
+    fn test_judge(
+        #[case] cards: &str,
+        #[case] trump: Option<Suit>,
+        #[case] n_round: u8,
+        #[case] expected: usize,
+        #[case] faces: &str,
+    ) -> anyhow::Result<()> {
+        let cards: TrickCards = crate::card::parse_hands(cards)?.into_iter().collect();
+        let (winner, face_cards) = judge(&cards, trump, n_round, &RuleSet::default());
+        assert_eq!(winner, expected);
+        assert_eq!(
+            winner,
+            trick_winner(&cards, trump, n_round, &RuleSet::default())
+        );
+        assert_eq!(face_cards, faces.parse()?);
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    #[case("SA HQ", None, 2, Preset::Simple, 0)]
+    #[case("D3 SJ", Some(Suit::Club), 2, Preset::Simple, 0)]
+    #[case("S3 S5 S2 S10 SK", None, 2, Preset::Simple, 4)]
+    #[case("D3 SA D5", None, 1, Preset::Classic, 2)]
+    #[case("D3 SA D5", None, 2, Preset::Classic, 1)]
+    #[case("D3 SA D5", None, 1, Preset::Standard, 1)]
+    fn test_trick_winner_rules(
+        #[case] cards: &str,
+        #[case] trump: Option<Suit>,
+        #[case] n_round: u8,
+        #[case] preset: Preset,
+        #[case] expected: usize,
+    ) -> anyhow::Result<()> {
+        let cards: TrickCards = crate::card::parse_hands(cards)?.into_iter().collect();
+        let rules = RuleSet::from(preset);
+        assert_eq!(trick_winner(&cards, trump, n_round, &rules), expected);
+        Ok(())
+    }
+
+    #[rstest::rstest]
+    #[test]
+    // マイティ以外には勝つ
+    #[case("D3 JK CJ D5 DA", Some(Suit::Club), 2, 1)]
+    #[case("D3 JK SA D5 DA", Some(Suit::Club), 2, 2)]
+    #[case("D3 JK HQ SA DA", Some(Suit::Club), 2, 2)]
+    // 最初に出せば負け、次のカードのスートが台札になる
+    #[case("JK D3 D5 C2 DA", None, 2, 4)]
+    #[case("JK D3 D5 C2 DA", Some(Suit::Club), 2, 3)]
+    // ジョーカーが入れば2は勝たない
+    #[case("D3 D2 JK", None, 2, 2)]
+    #[case("JK D3 D2 D5", None, 2, 3)]
+    // 最初のトリックで役札を使わなければ、スートのないジョーカーは勝たない
+    #[case("D3 JK D5", None, 1, 2)]
+    fn test_trick_winner_joker(
+        #[case] cards: &str,
+        #[case] trump: Option<Suit>,
+        #[case] n_round: u8,
+        #[case] expected: usize,
+    ) -> anyhow::Result<()> {
+        let cards: TrickCards = crate::card::parse_hands(cards)?.into_iter().collect();
+        let rules = RuleSet {
+            joker: true,
+            plain_first_trick: true,
+            ..Default::default()
+        };
+        assert_eq!(trick_winner(&cards, trump, n_round, &rules), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_trick_cards() -> anyhow::Result<()> {
+        let mut cards: TrickCards = crate::card::parse_hands("S3 S5 S2 S10 SK HA")?
+            .into_iter()
+            .collect();
+        assert_eq!(cards.len(), MAX_TRICK_CARDS);
+        cards.clear();
+        cards.push("D3".parse()?);
+        assert_eq!(&cards[..], &["D3".parse::<Card>()?]);
+        assert_eq!(cards, std::iter::once("D3".parse()?).collect());
+        Ok(())
+    }
+
     #[test]
     fn test_judge_winner_almighty() -> anyhow::Result<()> {
         let v: FieldCardIds = [1, 4, 24, 40, 52];
         let t = get_trick(&v);
-        let r = TrickResult::new(&t, None, 1)?;
+        let r = TrickResult::new(&t, None, 1, &RuleSet::default())?;
         assert_eq!(r.winner.id, "a");
         assert_eq!(
             r.face_cards,
@@ -180,7 +351,7 @@ mod tests {
     fn test_judge_winner_yoromeki() -> anyhow::Result<()> {
         let v: FieldCardIds = [1, 4, 25, 40, 52];
         let t = get_trick(&v);
-        let r = TrickResult::new(&t, None, 1)?;
+        let r = TrickResult::new(&t, None, 1, &RuleSet::default())?;
         assert_eq!(r.winner.id, "c");
         assert_eq!(
             r.face_cards,
@@ -198,7 +369,7 @@ mod tests {
     fn test_judge_winner_jack() -> anyhow::Result<()> {
         let v: FieldCardIds = [2, 11, 24, 40, 52];
         let t = get_trick(&v);
-        let r = TrickResult::new(&t, Some(Suit::Spade), 1)?;
+        let r = TrickResult::new(&t, Some(Suit::Spade), 1, &RuleSet::default())?;
         assert_eq!(r.winner.id, "b");
         assert_eq!(
             r.face_cards,
@@ -216,7 +387,7 @@ mod tests {
     fn test_judge_winner_rev_jack() -> anyhow::Result<()> {
         let v: FieldCardIds = [2, 4, 24, 40, 50];
         let t = get_trick(&v);
-        let r = TrickResult::new(&t, Some(Suit::Spade), 1)?;
+        let r = TrickResult::new(&t, Some(Suit::Spade), 1, &RuleSet::default())?;
         assert_eq!(r.winner.id, "e");
         assert_eq!(
             r.face_cards,
@@ -234,27 +405,48 @@ mod tests {
         let v: FieldCardIds = [2, 3, 4, 5, 6];
 
         let t = get_trick(&v);
-        let r = TrickResult::new(&t, None, 2)?;
+        let r = TrickResult::new(&t, None, 2, &RuleSet::default())?;
         assert_eq!(r.winner.id, "a");
         assert_eq!(r.face_cards, Vec::<Card>::new(),);
 
-        let r = TrickResult::new(&t, None, 1)?;
+        let r = TrickResult::new(&t, None, 1, &RuleSet::default())?;
         assert_eq!(r.winner.id, "e");
         assert_eq!(r.face_cards, Vec::<Card>::new(),);
         Ok(())
     }
 
+    #[rstest::rstest]
+    #[test]
+    // 切り札の3が台札のKに勝つ
+    #[case([13, 2, 29, 42, 5], Some(Suit::Diamond), "c")]
+    // 切り札同士はAが最も強い
+    #[case([13, 27, 29, 42, 5], Some(Suit::Diamond), "b")]
+    // 台札のAは切り札に負ける
+    #[case([14, 26, 29, 42, 5], Some(Suit::Diamond), "c")]
+    // 台札がAなら後から出たKには負けない
+    #[case([14, 26, 20, 42, 5], None, "a")]
+    #[case([14, 26, 20, 42, 5], Some(Suit::Club), "d")]
+    fn test_judge_winner_trump(
+        #[case] ids: FieldCardIds,
+        #[case] suit: Option<Suit>,
+        #[case] winner: &str,
+    ) -> anyhow::Result<()> {
+        let t = get_trick(&ids);
+        let r = TrickResult::new(&t, suit, 2, &RuleSet::default())?;
+        assert_eq!(r.winner.id, winner);
+        Ok(())
+    }
+
     #[test]
     fn test_to_json() -> anyhow::Result<()> {
         let v: FieldCardIds = [2, 4, 24, 40, 50];
         let t = get_trick(&v);
-        let r = TrickResult::new(&t, Some(Suit::Spade), 1)?;
+        let r = TrickResult::new(&t, Some(Suit::Spade), 1, &RuleSet::default())?;
         serde_json::to_string(&r)?;
         Ok(())
     }
 
     #[test]
-    #[ignore]
     fn test_from_json() -> anyhow::Result<()> {
         let v: FieldCardIds = [2, 4, 24, 40, 50];
         let trick = get_trick(&v).array()?;
@@ -0,0 +1,185 @@
+use crate::bidding::Bid;
+use crate::card::Card;
+use crate::card_set::CardSet;
+use crate::declaration::Declaration;
+use crate::error::{Error, Result};
+use crate::player::{Player, Role};
+use crate::round::{Phase, Round};
+use crate::rule::RuleSet;
+use crate::trick::Trick;
+use crate::trick_result::TrickResult;
+
+/// 観戦者に見える情報。誰の手札も含まない。
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SpectatorView {
+    pub phase: Phase,
+    pub rules: RuleSet,
+    pub players: Vec<Player>,
+    /// 競りの経過。`None`はパス。
+    pub bids: Vec<(Player, Option<Bid>)>,
+    pub declaration: Option<Declaration>,
+    /// 副官のカードが出ていれば、それを出したプレイヤー
+    pub aide: Option<Player>,
+    pub trick_results: Vec<TrickResult>,
+    pub trick: Trick,
+    pub next_player: Option<Player>,
+    /// 席順の残り手札の枚数
+    pub hand_counts: Vec<usize>,
+}
+
+impl SpectatorView {
+    pub fn new(round: &Round) -> Self {
+        SpectatorView {
+            phase: round.phase(),
+            rules: *round.rules(),
+            players: round
+                .field_players
+                .0
+                .iter()
+                .map(|p| p.player.clone())
+                .collect(),
+            bids: round.bidding().bids().to_vec(),
+            declaration: round.declaration().cloned(),
+            aide: round.revealed_aide(),
+            trick_results: round.trick_results().to_vec(),
+            trick: round.trick().clone(),
+            next_player: round.next_player(),
+            hand_counts: round
+                .field_players
+                .0
+                .iter()
+                .map(|p| p.hands.len())
+                .collect(),
+        }
+    }
+
+    /// 競りで一番高い立ちと、それを宣言したプレイヤー。
+    pub fn highest_bid(&self) -> Option<(&Player, Bid)> {
+        self.bids.iter().rev().find_map(|(p, b)| b.map(|b| (p, b)))
+    }
+}
+
+/// `player`に見える情報。自分の手札と、ナポレオンならオープンと捨て札を含む。
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PlayerView {
+    pub player: Player,
+    pub role: Role,
+    pub hands: CardSet,
+    pub opens: Option<Vec<Card>>,
+    pub discards: Option<Vec<Card>>,
+    /// 自分の手番なら出せるカード
+    pub legal_plays: Vec<Card>,
+    pub public: SpectatorView,
+}
+
+impl PlayerView {
+    pub fn new(round: &Round, player: &Player) -> Result<Self> {
+        let field_player = round
+            .field_players
+            .0
+            .iter()
+            .find(|p| p.player == *player)
+            .ok_or(Error::UnknownPlayer(player.clone()))?;
+        let mut public = SpectatorView::new(round);
+        // 副官は自分が副官であることを知っている
+        if field_player.role == Role::Aide {
+            public.aide = Some(player.clone());
+        }
+        let is_napoleon = field_player.role == Role::Napoleon;
+        let legal_plays = if public.next_player.as_ref() == Some(player) {
+            round.legal_plays()
+        } else {
+            Vec::new()
+        };
+        Ok(PlayerView {
+            player: player.clone(),
+            role: field_player.role.clone(),
+            hands: field_player.hands,
+            opens: (is_napoleon && round.phase() == Phase::Exchange).then(|| round.opens.clone()),
+            discards: round
+                .discards()
+                .filter(|_| is_napoleon)
+                .map(<[Card]>::to_vec),
+            legal_plays,
+            public,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::Players;
+    use crate::trick::Play;
+
+    #[test]
+    fn test_player_view() -> anyhow::Result<()> {
+        let players = Players::default();
+        let mut r = Round::new(players.clone())?;
+        let aide = r.field_players.0[1].hands.to_vec()[0];
+        r.set_declaration(Declaration::new(players.0[0].clone(), None, 13, aide)?)?;
+
+        let v = PlayerView::new(&r, &players.0[0])?;
+        assert_eq!(v.opens, Some(r.opens.clone()));
+        let v = PlayerView::new(&r, &players.0[2])?;
+        assert_eq!(v.opens, None);
+        assert_eq!(v.hands, r.field_players.0[2].hands);
+        assert_eq!(v.public.aide, None);
+        let v = PlayerView::new(&r, &players.0[1])?;
+        assert_eq!(v.role, Role::Aide);
+        assert_eq!(v.public.aide, Some(players.0[1].clone()));
+
+        r.exchange(&players.0[0], r.opens.clone())?;
+        let v = PlayerView::new(&r, &players.0[0])?;
+        assert_eq!(v.opens, None);
+        assert_eq!(v.discards, Some(r.opens.clone()));
+        assert_eq!(v.legal_plays, r.legal_plays());
+        assert!(PlayerView::new(&r, &players.0[2])?.legal_plays.is_empty());
+        assert!(PlayerView::new(
+            &r,
+            &Player {
+                id: "z".to_string()
+            }
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_spectator_view() -> anyhow::Result<()> {
+        let players = Players::default();
+        let mut r = Round::new(players.clone())?;
+        let aide = r.field_players.0[1].hands.to_vec()[0];
+        r.set_declaration(Declaration::new(players.0[0].clone(), None, 13, aide)?)?;
+        r.exchange(&players.0[0], r.opens.clone())?;
+
+        while r.revealed_aide().is_none() {
+            let player = r.next_player().unwrap();
+            let legal = r.legal_plays();
+            let card = if legal.contains(&aide) {
+                aide
+            } else {
+                legal[0]
+            };
+            r.play(Play::new(player, card))?;
+        }
+        let v = SpectatorView::new(&r);
+        assert!(v.bids.is_empty());
+        assert_eq!(v.highest_bid(), None);
+        assert_eq!(v.aide, Some(players.0[1].clone()));
+        assert_eq!(
+            v.hand_counts,
+            r.field_players
+                .0
+                .iter()
+                .map(|p| p.hands.len())
+                .collect::<Vec<usize>>()
+        );
+
+        // 誰の手札も含まれない
+        let json = serde_json::to_value(&v)?;
+        assert!(json.get("hands").is_none());
+        serde_json::from_value::<SpectatorView>(json)?;
+        Ok(())
+    }
+}
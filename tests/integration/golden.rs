@@ -0,0 +1,133 @@
+//! JSONの形が変わっていないことを確かめます。
+//! 形を変えたときは`NAPO_UPDATE_GOLDEN=1 cargo test`でファイルを書き直します。
+
+use napo::declaration::Declaration;
+use napo::game::Game;
+use napo::player::Players;
+use napo::round::Round;
+use napo::trick::Play;
+
+fn golden_path(name: &str) -> std::path::PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name)
+}
+
+fn check_golden<T>(name: &str, value: &T) -> anyhow::Result<()>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    let json = serde_json::to_string_pretty(value)? + "\n";
+    let path = golden_path(name);
+    if std::env::var_os("NAPO_UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, &json)?;
+    }
+    let golden = std::fs::read_to_string(&path)?;
+    assert_eq!(json, golden);
+
+    let restored: T = serde_json::from_str(&golden)?;
+    assert_eq!(serde_json::to_string_pretty(&restored)? + "\n", golden);
+    Ok(())
+}
+
+fn play(round: &mut Round, n: usize) -> anyhow::Result<()> {
+    for _ in 0..n {
+        let Some(player) = round.next_player() else {
+            break;
+        };
+        let card = round.legal_plays()[0];
+        round.play(Play::new(player, card))?;
+    }
+    Ok(())
+}
+
+fn start(round: &mut Round) -> anyhow::Result<()> {
+    let napoleon = round.field_players.0[0].player.clone();
+    let aide = round.field_players.0[1].hands.to_vec()[0];
+    round.set_declaration(Declaration::new(
+        napoleon.clone(),
+        Some(napo::card::Suit::Spade),
+        13,
+        aide,
+    )?)?;
+    round.exchange(&napoleon, round.opens.clone())?;
+    Ok(())
+}
+
+#[test]
+fn golden_round() -> anyhow::Result<()> {
+    let mut round = Round::new_with_seed(Players::default(), 1)?;
+    start(&mut round)?;
+    play(&mut round, 17)?;
+    check_golden("round.json", &round)?;
+    check_golden("trick.json", round.trick())?;
+    check_golden("trick_result.json", &round.trick_results()[0])?;
+    check_golden("events.json", &round.events().to_vec())
+}
+
+#[test]
+fn golden_game() -> anyhow::Result<()> {
+    let mut game = Game::new(Players::default())?;
+    let round = game.new_round_with_seed(2)?;
+    start(round)?;
+    play(round, 50)?;
+    game.settle()?;
+    let round = game.new_round_with_seed(3)?;
+    start(round)?;
+    play(round, 7)?;
+    check_golden("game.json", &game)
+}
+
+/// 移行した`new`が`old`の項目を全て同じ値で持っていることを確かめます。手札は並び順を問いません。
+fn assert_preserved(old: &serde_json::Value, new: &serde_json::Value, path: &str) {
+    use serde_json::Value;
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, value) in old {
+                let path = format!("{}/{}", path, key);
+                let new = new
+                    .get(key)
+                    .unwrap_or_else(|| panic!("{} is missing", path));
+                if key == "hands" {
+                    let sorted = |v: &Value| {
+                        let mut ids: Vec<u64> = v
+                            .as_array()
+                            .unwrap()
+                            .iter()
+                            .map(|c| c.as_u64().unwrap())
+                            .collect();
+                        ids.sort();
+                        ids
+                    };
+                    assert_eq!(sorted(value), sorted(new), "{}", path);
+                } else {
+                    assert_preserved(value, new, &path);
+                }
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            assert_eq!(old.len(), new.len(), "{}", path);
+            for (i, (old, new)) in old.iter().zip(new).enumerate() {
+                assert_preserved(old, new, &format!("{}/{}", path, i));
+            }
+        }
+        _ => assert_eq!(old, new, "{}", path),
+    }
+}
+
+#[test]
+fn golden_save_v0() -> anyhow::Result<()> {
+    // バージョンを持たず、競りの経過もない保存データも読み込めます。イベントの列は空になります
+    // save_v0.jsonは古い形式そのものなので書き直しません
+    let v0 = std::fs::read_to_string(golden_path("save_v0.json"))?;
+    let game = napo::save::from_json(&v0)?;
+    let migrated = serde_json::to_value(&game)?;
+    assert_preserved(&serde_json::from_str(&v0)?, &migrated, "");
+    let scores: Vec<i32> = game.player_scores().iter().map(|ps| ps.score).collect();
+    assert_eq!(scores, [-22, -11, 11, 11, 11]);
+    for round in game.rounds() {
+        assert!(round.events().is_empty());
+        assert_eq!(round.rules(), &napo::rule::RuleSet::default());
+    }
+    Ok(())
+}
@@ -1,16 +1,17 @@
 extern crate napo;
 
+mod golden;
+
 #[test]
-#[ignore]
 fn game() -> anyhow::Result<()> {
     // プレイヤーを揃えます
     let players = napo::player::Players::default();
 
     // ゲーム開始
-    let mut game = napo::game::Game::new(players);
+    let mut game = napo::game::Game::new(players)?;
 
     // 一回戦開始
-    let round = game.new_round();
+    let round = game.new_round()?;
 
     // プレイヤー0がナポレオンになります
     let napoleon = round.field_players.0[0].player.clone();
@@ -20,17 +21,26 @@ fn game() -> anyhow::Result<()> {
     let number = 15;
 
     // 副官を指名します
-    let aide_card = round.field_players.0[1].hands[0];
+    let aide_card = round.field_players.0[1].hands.to_vec()[0];
 
     // 立ちを宣言します
-    let declaration = napo::declaration::Declaration::new(napoleon, Some(suit), number, aide_card)?;
-
-    // let p = Play::new(
-    //     declaration.players[0],
-    //     game_cards.h
-    // )
-
+    let declaration =
+        napo::declaration::Declaration::new(napoleon.clone(), Some(suit), number, aide_card)?;
     round.set_declaration(declaration)?;
-    // println!("{:?}", round);
-    anyhow::bail!("hoge")
+
+    // オープンをそのまま捨てます
+    round.exchange(&napoleon, round.opens.clone())?;
+
+    // 出せるカードを順に出していきます
+    while let Some(player) = round.next_player() {
+        let card = round.legal_plays()[0];
+        round.play(napo::trick::Play::new(player, card))?;
+    }
+    assert_eq!(round.phase(), napo::round::Phase::Finished);
+    assert_eq!(round.trick_results().len(), 10);
+
+    // 精算します
+    let settlement = game.settle()?;
+    assert_eq!(settlement.deltas.iter().map(|(_, d)| d).sum::<i32>(), 0);
+    Ok(())
 }